@@ -0,0 +1,302 @@
+use anyhow::Result as AnyResult;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::imageops::FilterType;
+use image::io::Reader as ImageReader;
+use image::{Rgb, RgbImage, RgbaImage};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// cells are about twice as tall as wide, so 32 columns and 16 rows is square
+const ART_COLUMNS: u32 = 32;
+// in pixels, when the terminal doesn't tell its size
+const DEFAULT_CELL_SIZE: (u32, u32) = (8, 16);
+// kitty wants the image split in chunks of at most 4096 bytes
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// How the art is drawn in the terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArtFormat {
+    /// The kitty graphics protocol, also spoken by WezTerm and Ghostty
+    Kitty,
+    Sixel,
+    /// Two pixels per cell, with `▀` and true colors, works almost anywhere
+    Blocks,
+}
+
+impl FromStr for ArtFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kitty" => Ok(ArtFormat::Kitty),
+            "sixel" => Ok(ArtFormat::Sixel),
+            "blocks" => Ok(ArtFormat::Blocks),
+            _ => Err(format!(
+                "Invalid art format {s}, please use kitty, sixel or blocks"
+            )),
+        }
+    }
+}
+
+impl ArtFormat {
+    /// Guess what the terminal supports from its environment
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    fn from_env<F>(var: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+
+        if var("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "WezTerm"
+        {
+            return ArtFormat::Kitty;
+        }
+
+        let sixel_terms = ["foot", "mlterm", "yaft", "contour"];
+        if term.contains("sixel") || sixel_terms.iter().any(|t| term.starts_with(t)) {
+            return ArtFormat::Sixel;
+        }
+
+        ArtFormat::Blocks
+    }
+}
+
+/// The size of the art, in cells and in pixels
+struct ArtSize {
+    columns: u32,
+    rows: u32,
+    cell_width: u32,
+    cell_height: u32,
+}
+
+impl ArtSize {
+    fn of_terminal() -> Self {
+        let window = crossterm::terminal::window_size().ok();
+
+        let (cell_width, cell_height) = window
+            .as_ref()
+            .filter(|w| w.width > 0 && w.height > 0 && w.columns > 0 && w.rows > 0)
+            .map(|w| ((w.width / w.columns) as u32, (w.height / w.rows) as u32))
+            .filter(|(width, height)| *width > 0 && *height > 0)
+            .unwrap_or(DEFAULT_CELL_SIZE);
+
+        let columns = window
+            .as_ref()
+            .map(|w| w.columns as u32)
+            .filter(|columns| *columns > 0)
+            .unwrap_or(ART_COLUMNS)
+            .min(ART_COLUMNS);
+
+        ArtSize {
+            columns,
+            // as many rows as needed for it to be square
+            rows: (columns * cell_width / cell_height).max(1),
+            cell_width,
+            cell_height,
+        }
+    }
+}
+
+/// The album art, ready to be printed, using the same fetching and caching
+/// as the GUI
+pub fn render_art(art_url: &str, format: ArtFormat) -> AnyResult<String> {
+    let path = common::art::fetch_art(art_url)?;
+    let image = ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    let size = ArtSize::of_terminal();
+
+    let (width, height) = match format {
+        ArtFormat::Blocks => (size.columns, size.rows * 2),
+        _ => (size.columns * size.cell_width, size.rows * size.cell_height),
+    };
+    let image = image.resize_exact(width, height, FilterType::Triangle);
+
+    Ok(match format {
+        ArtFormat::Kitty => kitty(&image.to_rgba8(), size.columns, size.rows),
+        ArtFormat::Sixel => sixel(&image.to_rgb8()),
+        ArtFormat::Blocks => half_blocks(&image.to_rgb8()),
+    })
+}
+
+// the raw pixels, shown in `columns` and `rows` cells
+fn kitty(image: &RgbaImage, columns: u32, rows: u32) -> String {
+    let payload = BASE64.encode(image.as_raw());
+    let chunks = payload
+        .as_bytes()
+        .chunks(KITTY_CHUNK_SIZE)
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let more = if idx + 1 < chunks.len() { 1 } else { 0 };
+        let chunk = String::from_utf8_lossy(chunk);
+
+        if idx == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=32,s={},v={},c={},r={},m={};{}\x1b\\",
+                image.width(),
+                image.height(),
+                columns,
+                rows,
+                more,
+                chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    out
+}
+
+// every pixel becomes one of the 6x6x6 color cube, enough for a thumbnail
+fn cube_index(pixel: &Rgb<u8>) -> usize {
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])
+}
+
+// each char is a column of 6 pixels, long runs are shortened as `!<count><char>`
+fn push_sixels(out: &mut String, bits: &[u8]) {
+    let mut idx = 0;
+
+    while idx < bits.len() {
+        let run = bits[idx..].iter().take_while(|b| **b == bits[idx]).count();
+        let c = (63 + bits[idx]) as char;
+
+        if run > 3 {
+            out.push_str(&format!("!{}{}", run, c));
+        } else {
+            out.push_str(&c.to_string().repeat(run));
+        }
+        idx += run;
+    }
+}
+
+fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for idx in 0..216 {
+        // levels of 0 to 5, as percentages
+        let (r, g, b) = (idx / 36, idx / 6 % 6, idx % 6);
+        out.push_str(&format!("#{};2;{};{};{}", idx, r * 20, g * 20, b * 20));
+    }
+
+    for band_top in (0..height).step_by(6) {
+        let band_height = (height - band_top).min(6);
+
+        // the columns of the band, for each color used in it
+        let mut colors: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for x in 0..width {
+            for dy in 0..band_height {
+                let color = cube_index(image.get_pixel(x, band_top + dy));
+                colors
+                    .entry(color)
+                    .or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << dy;
+            }
+        }
+
+        for (color, bits) in &colors {
+            out.push_str(&format!("#{}", color));
+            push_sixels(&mut out, bits);
+            // back to the start of the band, for the next color
+            out.push('$');
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+fn half_blocks(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            let mut line = String::new();
+            for x in 0..width {
+                let top = image.get_pixel(x, y);
+                let bottom = image.get_pixel(x, (y + 1).min(height - 1));
+                line.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                ));
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_detect() {
+        let detect = |vars: &[(&str, &str)]| {
+            let vars = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>();
+            ArtFormat::from_env(|name| vars.get(name).cloned())
+        };
+
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), ArtFormat::Kitty);
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")]),
+            ArtFormat::Kitty
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), ArtFormat::Sixel);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), ArtFormat::Blocks);
+        assert_eq!(detect(&[]), ArtFormat::Blocks);
+    }
+
+    #[test]
+    fn test_push_sixels() {
+        let mut out = String::new();
+        push_sixels(&mut out, &[0, 0, 63, 63, 63, 63, 1]);
+        assert_eq!(out, "??!4~@");
+    }
+
+    #[test]
+    fn test_sixel() {
+        let image = RgbImage::from_pixel(2, 2, Rgb([255, 0, 0]));
+        let out = sixel(&image);
+
+        assert!(out.starts_with("\x1bPq\"1;1;2;2#0;2;0;0;0"));
+        // red is the color 180, and both columns have the top 2 pixels set
+        assert!(out.ends_with("#180BB$-\x1b\\"));
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        let mut image = RgbImage::from_pixel(1, 2, Rgb([255, 0, 0]));
+        image.put_pixel(0, 1, Rgb([0, 0, 255]));
+
+        assert_eq!(
+            half_blocks(&image),
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_kitty() {
+        let image = RgbaImage::from_pixel(1, 1, image::Rgba([1, 2, 3, 255]));
+
+        assert_eq!(
+            kitty(&image, 2, 1),
+            "\x1b_Ga=T,f=32,s=1,v=1,c=2,r=1,m=0;AQID/w==\x1b\\"
+        );
+    }
+}
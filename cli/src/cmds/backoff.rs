@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+const MIN_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Wait longer and longer between retries, so a broken D-Bus doesn't turn
+/// into a busy loop
+pub struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Backoff { delay: MIN_DELAY }
+    }
+
+    /// The delay to wait before the next retry, doubling it for the one after
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(MAX_DELAY);
+        delay
+    }
+
+    pub async fn wait(&mut self) {
+        tokio::time::sleep(self.next_delay()).await;
+    }
+
+    pub fn reset(&mut self) {
+        self.delay = MIN_DELAY;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new();
+
+        assert_eq!(backoff.next_delay(), Duration::from_millis(500));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+
+        for _ in 0..10 {
+            backoff.next_delay();
+        }
+        assert_eq!(backoff.next_delay(), MAX_DELAY);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), MIN_DELAY);
+    }
+}
@@ -0,0 +1,233 @@
+use super::output::{error_code, print_command_error, print_error, CommandError, ErrorCode};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult, RunError};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+// added to the flags of every command of a batch, it's not a real flag so it
+// can't be passed by hand
+const BATCH_MARKER: &str = "batch";
+
+// only one of them makes sense at a time
+const TARGET_FLAGS: [&str; 3] = ["player", "players", "all-players"];
+
+/// If the command runs as part of a batch
+pub fn is_batched(ctx: &CommandExecContext<CommandName>) -> bool {
+    ctx.args.flags.contains_key(BATCH_MARKER)
+}
+
+/// Split a line in commands, on `;`, and each command in args, on spaces.
+/// Quotes keep spaces and `;` in an arg, a backslash escapes the next char
+/// and `#` starts a comment.
+fn split_commands(line: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut commands = vec![];
+    let mut args = vec![];
+    // None between args, so `""` is still an (empty) arg
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(next) => arg.get_or_insert_with(String::new).push(next),
+                None => return Err(String::from("Nothing to escape at the end of the line")),
+            },
+            (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (None, '#') if arg.is_none() => break,
+            (None, ';') => {
+                args.extend(arg.take());
+                if !args.is_empty() {
+                    commands.push(std::mem::take(&mut args));
+                }
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("Missing closing quote {q}"));
+    }
+
+    args.extend(arg.take());
+    if !args.is_empty() {
+        commands.push(args);
+    }
+
+    Ok(commands)
+}
+
+/// Run the commands read from a file or stdin, one by one, in this process
+pub fn batch_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let input: Box<dyn BufRead> = match ctx.args.get(2).map(String::as_str) {
+        None | Some("-") => Box::new(io::stdin().lock()),
+        Some(path) => {
+            let file = File::open(path).map_err(|err| {
+                CommandError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Failed to open {path}: {err}"),
+                )
+            })?;
+            Box::new(BufReader::new(file))
+        }
+    };
+    let stop_on_error = ctx.args.flags.contains_key("stop-on-error");
+
+    common::player::share_connection().map_err(|err| {
+        CommandError::new(
+            ErrorCode::DBus,
+            format!("Failed to connect to D-Bus: {err}"),
+        )
+    })?;
+
+    // the code of the first error, the batch fails with it
+    let mut failed = None;
+
+    // line by line, so the commands run as soon as they're written to stdin
+    'lines: for line in input.lines() {
+        let line = line.map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to read the commands: {err}"),
+            )
+        })?;
+
+        let commands = match split_commands(&line) {
+            Ok(commands) => commands,
+            Err(err) => {
+                print_error(
+                    ctx.args.flags.contains_key("json"),
+                    ErrorCode::InvalidArgument,
+                    format!("Invalid command: {err}"),
+                );
+                failed.get_or_insert(ErrorCode::InvalidArgument);
+                if stop_on_error {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        for args in commands {
+            if let Err(code) = run_command(&ctx, args) {
+                failed.get_or_insert(code);
+                if stop_on_error {
+                    break 'lines;
+                }
+            }
+        }
+    }
+
+    // every error is already printed
+    match failed {
+        Some(code) => Err(CommandError::printed(code).into()),
+        None => Ok(()),
+    }
+}
+
+// run one command of the batch, which prints its output or its error
+fn run_command(ctx: &CommandExecContext<CommandName>, args: Vec<String>) -> Result<(), ErrorCode> {
+    let program_name = std::iter::once(ctx.app.name.clone());
+    let parsed = ctx.app.arg_parser.parse_from_iter(program_name.chain(args));
+
+    let mut parsed = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            print_error(
+                ctx.args.flags.contains_key("json"),
+                ErrorCode::InvalidArgument,
+                err,
+            );
+            return Err(ErrorCode::InvalidArgument);
+        }
+    };
+
+    // the flags of the batch, like --player or --json, are the defaults of
+    // every command
+    let targets_player = TARGET_FLAGS
+        .iter()
+        .any(|flag| parsed.flags.contains_key(*flag));
+    for flag in &ctx.app.flags {
+        if targets_player && TARGET_FLAGS.contains(&flag.name) {
+            continue;
+        }
+        if let Some(value) = ctx.args.flags.get(flag.name) {
+            parsed
+                .flags
+                .entry(flag.name.to_string())
+                .or_insert_with(|| value.clone());
+        }
+    }
+    parsed.flags.insert(BATCH_MARKER.to_string(), String::new());
+
+    let as_json = parsed.flags.contains_key("json");
+
+    let name = parsed
+        .get(1)
+        .and_then(|name| name.parse::<CommandName>().ok());
+    if let Some(
+        name @ (CommandName::Batch | CommandName::Tui | CommandName::Waybar | CommandName::Daemon),
+    ) = name
+    {
+        print_error(
+            as_json,
+            ErrorCode::InvalidArgument,
+            format!("The {name} command can't be used in a batch"),
+        );
+        return Err(ErrorCode::InvalidArgument);
+    }
+
+    match ctx.app.run_args(parsed) {
+        Ok(_) => Ok(()),
+        Err(RunError::Failed(err)) => {
+            print_command_error(as_json, &err);
+            Err(error_code(&err))
+        }
+        Err(err) => {
+            print_error(as_json, ErrorCode::InvalidArgument, err);
+            Err(ErrorCode::InvalidArgument)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(commands: &[&[&str]]) -> Vec<Vec<String>> {
+        commands
+            .iter()
+            .map(|args| args.iter().map(|arg| arg.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_commands() {
+        assert_eq!(
+            split_commands("volume 0.3; next;position 30000"),
+            Ok(commands(&[
+                &["volume", "0.3"],
+                &["next"],
+                &["position", "30000"]
+            ]))
+        );
+        assert_eq!(
+            split_commands(r#"open "My Song; Live.flac" --player='mpv'"#),
+            Ok(commands(&[&["open", "My Song; Live.flac", "--player=mpv"]]))
+        );
+        assert_eq!(
+            split_commands(r"open My\ Song.flac"),
+            Ok(commands(&[&["open", "My Song.flac"]]))
+        );
+        assert_eq!(split_commands("next # skip it"), Ok(commands(&[&["next"]])));
+        assert_eq!(split_commands(" ;; # only a comment"), Ok(vec![]));
+
+        assert!(split_commands(r#"open "song.flac"#).is_err());
+    }
+}
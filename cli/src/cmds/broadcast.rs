@@ -0,0 +1,199 @@
+use super::output::{
+    fail, format_error, print_line, wants_json, CommandError, CommandOutput, ErrorCode,
+};
+use super::utils::{action_error, lookup_error, player_json};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use serde_json::{json, Value};
+
+/// What an action did on one of the players
+struct PlayerResult {
+    player: Value,
+    /// Identity and bus name, to tell the players apart in text mode
+    label: String,
+    result: Result<CommandOutput, CommandError>,
+}
+
+/// If the command targets many players, with --all-players or --players
+pub fn is_broadcast(ctx: &CommandExecContext<CommandName>) -> bool {
+    ctx.args.flags.contains_key("all-players") || ctx.args.flags.contains_key("players")
+}
+
+// "spotify, firefox.*" -> ["spotify", "firefox.*"]
+fn split_patterns(patterns: &str) -> Vec<&str> {
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+fn find_players(ctx: &CommandExecContext<CommandName>) -> Result<Vec<mpris::Player>, CommandError> {
+    let flags = &ctx.args.flags;
+
+    if flags.contains_key("player")
+        || (flags.contains_key("all-players") && flags.contains_key("players"))
+    {
+        return fail(
+            ErrorCode::InvalidArgument,
+            "Only one of --player, --players and --all-players can be used",
+        );
+    }
+
+    let (players, no_player_message) = match flags.get("players") {
+        Some(patterns) => {
+            let patterns = split_patterns(patterns);
+            if patterns.is_empty() {
+                return fail(ErrorCode::InvalidArgument, "No player pattern provided");
+            }
+            (
+                common::player::find_players_matching(&patterns),
+                format!("No player matches {}", patterns.join(", ")),
+            )
+        }
+        None => (
+            common::player::find_all_players(),
+            String::from("No player found"),
+        ),
+    };
+
+    let players = players.map_err(|err| lookup_error("Failed to list players", err))?;
+
+    if players.is_empty() {
+        return fail(ErrorCode::NoPlayer, no_player_message);
+    }
+
+    Ok(players)
+}
+
+/// Run the action on every targeted player, print one result per player and
+/// fail if any of them failed
+pub fn broadcast<F>(
+    ctx: &CommandExecContext<CommandName>,
+    action_name: &str,
+    action: F,
+) -> CommandResult
+where
+    F: Fn(&mpris::Player) -> Result<CommandOutput, CommandError>,
+{
+    let results = find_players(ctx)?
+        .iter()
+        .map(|player| PlayerResult {
+            player: player_json(player),
+            label: format!("{} ({})", player.identity(), player.bus_name()),
+            result: action(player),
+        })
+        .collect::<Vec<_>>();
+
+    if wants_json(ctx) {
+        print_line(results_json(action_name, &results));
+    } else {
+        for result in &results {
+            match result.result {
+                Ok(_) => print_line(result_text(action_name, result)),
+                Err(_) => eprintln!("{}", result_text(action_name, result)),
+            }
+        }
+    }
+
+    // every error is already printed
+    match results
+        .iter()
+        .find_map(|result| result.result.as_ref().err())
+    {
+        Some(err) => Err(CommandError::printed(err.code).into()),
+        None => Ok(()),
+    }
+}
+
+fn result_text(action_name: &str, result: &PlayerResult) -> String {
+    match &result.result {
+        Err(err) => format!(
+            "Failed to call action {action_name} on player {}: {err}",
+            result.label
+        ),
+        Ok(output) => match output.text() {
+            None => format!("Action {action_name} called on player {}", result.label),
+            Some(text) if text.contains('\n') => {
+                format!("{}:\n  {}", result.label, text.replace('\n', "\n  "))
+            }
+            Some(text) => format!("{}: {}", result.label, text),
+        },
+    }
+}
+
+fn results_json(action_name: &str, results: &[PlayerResult]) -> Value {
+    let results = results
+        .iter()
+        .map(|result| match &result.result {
+            Ok(output) => json!({ "player": result.player, "result": output.json() }),
+            Err(err) => {
+                let err = action_error(action_name, err.clone());
+                let mut value = format_error(err.code, err.message);
+                value["player"] = result.player.clone();
+                value
+            }
+        })
+        .collect::<Vec<_>>();
+
+    json!({ "action": action_name, "results": results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_result(result: Result<CommandOutput, CommandError>) -> PlayerResult {
+        PlayerResult {
+            player: json!({ "name": "org.mpris.MediaPlayer2.mpv", "identity": "mpv" }),
+            label: String::from("mpv (org.mpris.MediaPlayer2.mpv)"),
+            result,
+        }
+    }
+
+    #[test]
+    fn test_split_patterns() {
+        assert_eq!(
+            split_patterns("spotify, firefox.*,,"),
+            vec!["spotify", "firefox.*"]
+        );
+    }
+
+    #[test]
+    fn test_result_text() {
+        let ok = new_result(Ok(CommandOutput::new("0.5", json!({}))));
+        assert_eq!(
+            result_text("volume", &ok),
+            "mpv (org.mpris.MediaPlayer2.mpv): 0.5"
+        );
+
+        let silent = new_result(Ok(CommandOutput::silent(json!({}))));
+        assert_eq!(
+            result_text("pause", &silent),
+            "Action pause called on player mpv (org.mpris.MediaPlayer2.mpv)"
+        );
+
+        let failed = new_result(Err(CommandError::new(ErrorCode::DBus, "not supported")));
+        assert_eq!(
+            result_text("next", &failed),
+            "Failed to call action next on player mpv (org.mpris.MediaPlayer2.mpv): not supported"
+        );
+    }
+
+    #[test]
+    fn test_results_json() {
+        let results = [
+            new_result(Ok(CommandOutput::new("0.5", json!({ "volume": 0.5 })))),
+            new_result(Err(CommandError::new(ErrorCode::DBus, "not supported"))),
+        ];
+
+        let value = results_json("volume", &results);
+
+        assert_eq!(value["results"][0]["result"], json!({ "volume": 0.5 }));
+        assert_eq!(value["results"][1]["error"]["code"], "dbus_error");
+        assert_eq!(
+            value["results"][1]["player"]["name"],
+            "org.mpris.MediaPlayer2.mpv"
+        );
+    }
+}
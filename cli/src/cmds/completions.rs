@@ -0,0 +1,48 @@
+use super::output::{fail, print_line, print_raw, CommandError, ErrorCode};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult, Shell};
+
+pub fn completions_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    match ctx.args.get(2).map(String::as_str) {
+        None => fail(
+            ErrorCode::InvalidArgument,
+            "Shell not provided, please use bash, zsh or fish",
+        ),
+        // used by the completion scripts themselves
+        Some("players") => print_player_names(),
+        Some("commands") => {
+            for cmd in ctx.app.ordered_commands() {
+                print_line(&cmd.name);
+            }
+            Ok(())
+        }
+        Some(shell) => {
+            let shell: Shell = shell
+                .parse()
+                .map_err(|e| CommandError::new(ErrorCode::InvalidArgument, e))?;
+
+            print_raw(ctx.app.completions(shell));
+            Ok(())
+        }
+    }
+}
+
+fn print_player_names() -> CommandResult {
+    let players = common::player::find_all_players().map_err(|err| {
+        CommandError::new(ErrorCode::DBus, format!("Failed to list players: {err}"))
+    })?;
+
+    for player in players {
+        print_line(player.bus_name());
+    }
+
+    // a broken config is not worth an error while completing
+    if let Ok(config) = common::config::load_config() {
+        let mut aliases = config.aliases.into_keys().collect::<Vec<_>>();
+        aliases.sort();
+        for alias in aliases {
+            print_line(alias);
+        }
+    }
+    Ok(())
+}
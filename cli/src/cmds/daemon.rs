@@ -0,0 +1,31 @@
+use super::output::{fail, CommandError, ErrorCode};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+
+pub fn daemon_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    if ctx.args.flags.contains_key("player") {
+        return fail(
+            ErrorCode::InvalidArgument,
+            "Daemon mode does not support the --player flag",
+        );
+    };
+
+    let config = common::config::load_config().map_err(|err| {
+        CommandError::new(ErrorCode::Failed, format!("Failed to load config: {err:#}"))
+    })?;
+
+    let handle = common::player::spawn_policies(&config).map_err(|err| {
+        CommandError::new(
+            ErrorCode::Failed,
+            format!("Failed to start the policies: {err:#}"),
+        )
+    })?;
+
+    match handle {
+        Some(handle) => match handle.join() {
+            Ok(_) => Ok(()),
+            Err(_) => fail(ErrorCode::Failed, "Policies thread crashed"),
+        },
+        None => fail(ErrorCode::Failed, "No policy enabled in the config file"),
+    }
+}
@@ -0,0 +1,204 @@
+use super::backoff::Backoff;
+use super::broadcast::is_broadcast;
+use super::output::{fail, wants_json, CommandError, CommandOutput, ErrorCode};
+use super::printer::Printer;
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use anyhow::Result as AnyResult;
+use common::player::{MprisWrapper, PlayerState};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::MissedTickBehavior;
+
+// the position is not sent by the listener, so it's queried this often
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Check for the --follow flag, which only makes sense when reading a value
+pub fn should_follow(
+    ctx: &CommandExecContext<CommandName>,
+    value: Option<&String>,
+) -> Result<bool, CommandError> {
+    if !ctx.args.flags.contains_key("follow") {
+        return Ok(false);
+    }
+
+    if value.is_some() {
+        return fail(
+            ErrorCode::InvalidArgument,
+            "The --follow flag can't be used when setting a value",
+        );
+    }
+
+    if is_broadcast(ctx) {
+        return fail(
+            ErrorCode::InvalidArgument,
+            "The --follow flag can't be used with --all-players or --players",
+        );
+    }
+
+    Ok(true)
+}
+
+/// Keep printing the value returned by `query`, every time it changes.
+///
+/// Without `--player`, the preferred player is followed, so switching it
+/// prints the value of the new one.
+pub fn follow<F>(ctx: &CommandExecContext<CommandName>, query: F) -> CommandResult
+where
+    F: Fn(&mpris::Player, &PlayerState) -> CommandOutput,
+{
+    let fixed_player = ctx.args.flags.get("player").cloned();
+    let as_json = wants_json(ctx);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to start the runtime: {err}"),
+            )
+        })?;
+
+    runtime.block_on(follow_loop(fixed_player, as_json, query))?;
+    Ok(())
+}
+
+// only returns when it can't go on
+async fn follow_loop<F>(
+    fixed_player: Option<String>,
+    as_json: bool,
+    query: F,
+) -> Result<(), CommandError>
+where
+    F: Fn(&mpris::Player, &PlayerState) -> CommandOutput,
+{
+    let mut printer = Printer::new();
+    let mut backoff = Backoff::new();
+
+    // there's nothing to watch when the player is fixed
+    let mut listener = common::player::PreferredPlayerListener::new();
+    let mut player_rx = match fixed_player {
+        Some(_) => None,
+        None => match listener.start() {
+            Ok(rx) => Some(rx),
+            Err(err) => {
+                return fail(
+                    ErrorCode::DBus,
+                    format!("Failed to watch the preferred player: {err:#}"),
+                )
+            }
+        },
+    };
+
+    loop {
+        let player = match &fixed_player {
+            Some(name) => common::player::find_player(name),
+            None => common::player::get_preferred_player_or_first(),
+        };
+
+        let player = match player {
+            Ok(Some(player)) => player,
+            // wait for it to show up
+            Ok(None) => {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+            Err(err) => {
+                eprintln!("Failed to get player: {err:#}");
+                backoff.wait().await;
+                continue;
+            }
+        };
+
+        let (event_tx, event_rx) = mpsc::channel(1);
+        if let Err(err) = MprisWrapper::new(player.bus_name().to_string()).start_listener(event_tx)
+        {
+            eprintln!("Failed to listen to {}: {err:#}", player.bus_name());
+            backoff.wait().await;
+            continue;
+        }
+
+        let had_state = follow_player(
+            &player,
+            &query,
+            as_json,
+            &mut printer,
+            event_rx,
+            player_rx.as_mut(),
+        )
+        .await?;
+
+        // the player went away before saying anything, don't hammer it
+        if had_state {
+            backoff.reset();
+        } else {
+            backoff.wait().await;
+        }
+    }
+}
+
+async fn follow_player<F>(
+    player: &mpris::Player,
+    query: &F,
+    as_json: bool,
+    printer: &mut Printer,
+    mut event_rx: Receiver<PlayerState>,
+    mut player_rx: Option<&mut Receiver<AnyResult<String>>>,
+) -> Result<bool, CommandError>
+where
+    F: Fn(&mpris::Player, &PlayerState) -> CommandOutput,
+{
+    let mut last_state: Option<PlayerState> = None;
+
+    let mut ticker = tokio::time::interval(POLL_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            state = event_rx.recv() => {
+                match state {
+                    Some(state) => {
+                        print_value(printer, query(player, &state), as_json);
+                        last_state = Some(state);
+                    }
+                    None => return Ok(last_state.is_some()),
+                }
+            },
+            _ = ticker.tick() => {
+                if let Some(state) = &last_state {
+                    print_value(printer, query(player, state), as_json);
+                }
+            },
+            new_player_name = recv_preferred(&mut player_rx) => {
+                match new_player_name {
+                    Some(Ok(name)) if name.trim() != player.bus_name() => return Ok(true),
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => eprintln!("Failed to read preferred player: {err:#}"),
+                    None => return fail(ErrorCode::DBus, "Stopped watching the preferred player"),
+                }
+            }
+        }
+    }
+}
+
+fn print_value(printer: &mut Printer, output: CommandOutput, as_json: bool) {
+    if let Some(mut line) = output.render(as_json) {
+        // keep multi-line values, like the whole metadata, apart
+        if line.contains('\n') {
+            line.push('\n');
+        }
+        printer.print(line);
+    }
+}
+
+// never resolves if there's nothing to watch
+async fn recv_preferred(
+    player_rx: &mut Option<&mut Receiver<AnyResult<String>>>,
+) -> Option<AnyResult<String>> {
+    match player_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,79 @@
+use super::output::{print_output, print_raw, wants_json, CommandError, CommandOutput, ErrorCode};
+use super::CommandName;
+use crate::core_definition::{Command, CommandExecContext, CommandFlag, CommandResult};
+use serde_json::{json, Value};
+
+pub fn help_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    if let Some(cmd_name) = ctx.args.get(2) {
+        let cmd = cmd_name
+            .parse::<CommandName>()
+            .ok()
+            .and_then(|cmd_name| ctx.app.commands.get(&cmd_name))
+            .ok_or_else(|| {
+                CommandError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Unknown command {cmd_name}"),
+                )
+            })?;
+
+        print_output(
+            &ctx,
+            CommandOutput::new(ctx.app.command_help_text(cmd).trim_end(), command_json(cmd)),
+        );
+        return Ok(());
+    }
+
+    if !wants_json(&ctx) {
+        print_raw(ctx.app.help_text());
+        return Ok(());
+    }
+
+    print_output(
+        &ctx,
+        CommandOutput::silent(json!({
+            "name": ctx.app.name,
+            "description": ctx.app.description,
+            "flags": ctx.app.flags.iter().map(|flag| flag_json(flag)).collect::<Vec<_>>(),
+            "groups": ctx.app.grouped_commands().into_iter().map(|(title, commands)| json!({
+                "title": title,
+                "commands": commands.into_iter().map(command_json).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "exit_codes": ctx.app.exit_codes.iter().map(|(code, description)| json!({
+                "code": code,
+                "description": description,
+            })).collect::<Vec<_>>(),
+        })),
+    );
+    Ok(())
+}
+
+fn command_json(cmd: &Command<CommandName>) -> Value {
+    json!({
+        "name": cmd.name.to_string(),
+        "usage": cmd.usage,
+        "description": cmd.description,
+        "flags": cmd.flags.iter().map(|flag| flag_json(flag)).collect::<Vec<_>>(),
+        "args": cmd.args.iter().map(|(name, description)| json!({
+            "name": name,
+            "description": description,
+        })).collect::<Vec<_>>(),
+        "examples": cmd.examples.iter().map(|(command_line, description)| json!({
+            "command": command_line,
+            "description": description,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn flag_json(flag: &CommandFlag) -> Value {
+    json!({
+        "name": flag.name,
+        "short_name": flag.short_name,
+        "description": flag.description,
+        "has_value": flag.has_value,
+    })
+}
+
+pub fn manpage_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    print_raw(ctx.app.manpage());
+    Ok(())
+}
@@ -1,8 +1,31 @@
+mod art;
+mod backoff;
+mod batch;
+mod broadcast;
+mod completions;
+mod daemon;
+mod follow;
+mod help;
 mod names;
+mod open;
+mod output;
 mod player;
+mod printer;
+mod seek;
+mod tui;
 mod utils;
+mod wait;
 mod waybar;
 
+pub use batch::batch_cmd;
+pub use completions::*;
+pub use daemon::*;
+pub use help::*;
 pub use names::CommandName;
+pub use open::open_cmd;
+pub use output::{error_code, print_command_error, print_error, print_raw, ErrorCode};
 pub use player::*;
+pub use seek::seek_cmd;
+pub use tui::tui_cmd;
+pub use wait::wait_cmd;
 pub use waybar::*;
@@ -19,10 +19,18 @@ pub enum CommandName {
     Volume,
     Metadata,
     Position,
+    Seek,
+    Open,
     Loop,
     Shuffle,
     Show,
     Waybar,
+    Tui,
+    Batch,
+    Wait,
+    Daemon,
+    Completions,
+    Manpage,
 }
 
 impl CommandName {
@@ -43,10 +51,18 @@ impl CommandName {
             CommandName::Volume => "volume",
             CommandName::Metadata => "metadata",
             CommandName::Position => "position",
+            CommandName::Seek => "seek",
+            CommandName::Open => "open",
             CommandName::Loop => "loop",
             CommandName::Shuffle => "shuffle",
             CommandName::Show => "show",
             CommandName::Waybar => "waybar",
+            CommandName::Tui => "tui",
+            CommandName::Batch => "batch",
+            CommandName::Wait => "wait",
+            CommandName::Daemon => "daemon",
+            CommandName::Completions => "completions",
+            CommandName::Manpage => "manpage",
         }
     }
 }
@@ -69,12 +85,20 @@ impl FromStr for CommandName {
             "set-player" => Ok(CommandName::SetPlayer),
             "raise" => Ok(CommandName::Raise),
             "position" => Ok(CommandName::Position),
+            "seek" => Ok(CommandName::Seek),
+            "open" => Ok(CommandName::Open),
             "volume" => Ok(CommandName::Volume),
             "metadata" => Ok(CommandName::Metadata),
             "waybar" => Ok(CommandName::Waybar),
+            "tui" => Ok(CommandName::Tui),
+            "batch" => Ok(CommandName::Batch),
+            "wait" => Ok(CommandName::Wait),
             "loop" => Ok(CommandName::Loop),
             "shuffle" => Ok(CommandName::Shuffle),
             "show" => Ok(CommandName::Show),
+            "daemon" => Ok(CommandName::Daemon),
+            "completions" => Ok(CommandName::Completions),
+            "manpage" => Ok(CommandName::Manpage),
             _ => Err(()),
         }
     }
@@ -99,7 +123,7 @@ mod tests {
 
             let cmd_from_str: CommandName = value
                 .parse()
-                .expect(format!("CommandName cannot be parsed from value {value}").as_str());
+                .unwrap_or_else(|_| panic!("CommandName cannot be parsed from value {value}"));
 
             assert_eq!(value, cmd_from_str.value());
         }
@@ -0,0 +1,61 @@
+use super::output::{fail, CommandError, CommandOutput, ErrorCode};
+use super::utils::{exec_player_action_silent, player_json};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use common::player::{find_player_for_uri, open_uri, player_can_open, to_uri};
+use serde_json::json;
+
+fn open_output(player: &mpris::Player, uri: &str) -> CommandOutput {
+    CommandOutput::new(
+        format!(
+            "Opened {uri} in player {} ({})",
+            player.identity(),
+            player.bus_name()
+        ),
+        json!({ "action": "open", "player": player_json(player), "uri": uri }),
+    )
+}
+
+pub fn open_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let arg = ctx
+        .args
+        .get(2)
+        .ok_or_else(|| CommandError::new(ErrorCode::InvalidArgument, "URI or path not provided"))?;
+
+    let uri = to_uri(arg).map_err(|err| {
+        CommandError::new(
+            ErrorCode::InvalidArgument,
+            format!("Invalid URI or path: {err:#}"),
+        )
+    })?;
+    let any_player = ctx.args.flags.contains_key("any-player");
+
+    exec_player_action_silent(&ctx, "open", |player| {
+        // many browsers don't say what they can open, so they can't
+        if player_can_open(player, &uri).unwrap_or(false) {
+            open_uri(player, &uri)?;
+            return Ok(open_output(player, &uri));
+        }
+
+        if !any_player {
+            return fail(
+                ErrorCode::Unsupported,
+                format!(
+                    "Player {} can't open {uri}, use --any-player to open it in one that can",
+                    player.bus_name()
+                ),
+            );
+        }
+
+        let other = find_player_for_uri(&uri)
+            .map_err(|err| {
+                CommandError::new(ErrorCode::DBus, format!("Failed to list players: {err}"))
+            })?
+            .ok_or_else(|| {
+                CommandError::new(ErrorCode::Unsupported, format!("No player can open {uri}"))
+            })?;
+
+        open_uri(&other, &uri)?;
+        Ok(open_output(&other, &uri))
+    })
+}
@@ -0,0 +1,261 @@
+use super::batch::is_batched;
+use super::CommandName;
+use crate::core_definition::CommandExecContext;
+use mpris::DBusError;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::process;
+
+/// What a command prints, as text or as JSON with the --json flag
+pub struct CommandOutput {
+    text: Option<String>,
+    json: Value,
+}
+
+impl CommandOutput {
+    pub fn new(text: impl Into<String>, json: Value) -> Self {
+        CommandOutput {
+            text: Some(text.into()),
+            json,
+        }
+    }
+
+    /// Nothing to say in text mode, like after setting a value
+    pub fn silent(json: Value) -> Self {
+        CommandOutput { text: None, json }
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn json(&self) -> &Value {
+        &self.json
+    }
+
+    pub fn render(&self, as_json: bool) -> Option<String> {
+        if as_json {
+            Some(self.json.to_string())
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// Why a command failed, the code is part of the JSON output so scripts can
+/// rely on it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    NoPlayer,
+    PlayerNotFound,
+    Unsupported,
+    InvalidArgument,
+    DBus,
+    /// The condition of `wait` didn't hold in time
+    Timeout,
+    Failed,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 7] = [
+        ErrorCode::Failed,
+        ErrorCode::InvalidArgument,
+        ErrorCode::NoPlayer,
+        ErrorCode::PlayerNotFound,
+        ErrorCode::Unsupported,
+        ErrorCode::DBus,
+        ErrorCode::Timeout,
+    ];
+
+    /// The exit status of the process when a command fails with it
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCode::Failed => 1,
+            ErrorCode::InvalidArgument => 2,
+            ErrorCode::NoPlayer => 3,
+            ErrorCode::PlayerNotFound => 4,
+            ErrorCode::Unsupported => 5,
+            ErrorCode::DBus => 6,
+            ErrorCode::Timeout => 7,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::Failed => "any other error, like a broken config file",
+            ErrorCode::InvalidArgument => "an invalid command, flag or argument",
+            ErrorCode::NoPlayer => "no player is running",
+            ErrorCode::PlayerNotFound => "the player from --player is not running",
+            ErrorCode::Unsupported => "the player can't do it, like seeking in a stream",
+            ErrorCode::DBus => "D-Bus or the player failed to answer",
+            ErrorCode::Timeout => "the condition of wait didn't hold in time",
+        }
+    }
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            ErrorCode::NoPlayer => "no_player",
+            ErrorCode::PlayerNotFound => "player_not_found",
+            ErrorCode::Unsupported => "unsupported",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::DBus => "dbus_error",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Failed => "failed",
+        }
+    }
+}
+
+pub fn wants_json(ctx: &CommandExecContext<CommandName>) -> bool {
+    ctx.args.flags.contains_key("json")
+}
+
+/// Write to stdout, like `print!`. Once stdout is closed, like in
+/// `rstroller list | head -1`, nobody reads the output anymore, so the
+/// process ends quietly instead of panicking like `print!` does.
+pub fn print_raw(text: impl Display) {
+    let mut stdout = io::stdout().lock();
+
+    match write!(stdout, "{}", text).and_then(|_| stdout.flush()) {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(err) => {
+            eprintln!("Failed to print the output: {}", err);
+            process::exit(ErrorCode::Failed.exit_code());
+        }
+    }
+}
+
+/// Write a line to stdout, see `print_raw`
+pub fn print_line(line: impl Display) {
+    print_raw(format_args!("{}\n", line));
+}
+
+pub fn print_output(ctx: &CommandExecContext<CommandName>, output: CommandOutput) {
+    match output.render(wants_json(ctx)) {
+        Some(line) => print_line(line),
+        // a batch prints one result per command, even when there's nothing to say
+        None if is_batched(ctx) => print_line("OK"),
+        None => {}
+    }
+}
+
+/// Why a command failed
+#[derive(Debug, Clone)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Nothing is left to print, like after a broadcast printed the error of
+    /// each player
+    pub printed: bool,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Display) -> Self {
+        CommandError {
+            code,
+            message: message.to_string(),
+            printed: false,
+        }
+    }
+
+    pub fn printed(code: ErrorCode) -> Self {
+        CommandError {
+            code,
+            message: String::new(),
+            printed: true,
+        }
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<DBusError> for CommandError {
+    fn from(err: DBusError) -> Self {
+        CommandError::new(ErrorCode::DBus, err)
+    }
+}
+
+/// Stop with an error, as in `return fail(ErrorCode::Unsupported, "The player can't seek")`
+pub fn fail<T, E>(code: ErrorCode, message: impl Display) -> Result<T, E>
+where
+    E: From<CommandError>,
+{
+    Err(CommandError::new(code, message).into())
+}
+
+/// The code of an error returned by a command, anything but a `CommandError`
+/// is a failure without a specific code
+pub fn error_code(err: &anyhow::Error) -> ErrorCode {
+    err.downcast_ref::<CommandError>()
+        .map_or(ErrorCode::Failed, |err| err.code)
+}
+
+/// Print an error returned by a command, see `error_code`
+pub fn print_command_error(as_json: bool, err: &anyhow::Error) {
+    match err.downcast_ref::<CommandError>() {
+        Some(err) if err.printed => {}
+        Some(err) => print_error(as_json, err.code, &err.message),
+        None => print_error(as_json, ErrorCode::Failed, format!("{err:#}")),
+    }
+}
+
+pub fn print_error(as_json: bool, code: ErrorCode, message: impl Display) {
+    if as_json {
+        print_line(format_error(code, message));
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+pub fn format_error(code: ErrorCode, message: impl Display) -> Value {
+    json!({
+        "error": {
+            "code": code.value(),
+            "message": message.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let output = CommandOutput::new("Playing", json!({ "status": "Playing" }));
+        assert_eq!(output.render(false), Some("Playing".to_string()));
+        assert_eq!(
+            output.render(true),
+            Some(r#"{"status":"Playing"}"#.to_string())
+        );
+
+        let output = CommandOutput::silent(json!({}));
+        assert_eq!(output.render(false), None);
+        assert_eq!(output.render(true), Some("{}".to_string()));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let mut codes = ErrorCode::ALL.map(|code| code.exit_code()).to_vec();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+        assert!(!codes.contains(&0));
+    }
+
+    #[test]
+    fn test_format_error() {
+        assert_eq!(
+            format_error(ErrorCode::NoPlayer, "No player found"),
+            json!({ "error": { "code": "no_player", "message": "No player found" } })
+        );
+    }
+}
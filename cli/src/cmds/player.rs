@@ -1,275 +1,500 @@
+use super::art::{render_art, ArtFormat};
+use super::follow::{follow, should_follow};
+use super::output::{fail, print_output, wants_json, CommandError, CommandOutput, ErrorCode};
+use super::seek::seek;
 use super::utils::{
-    exec_player_action, exec_player_action_silent, format_metadata_value, parse_offset, print_if_ok,
+    exec_player_action, exec_player_action_silent, find_player, format_if_ok,
+    format_metadata_block, format_metadata_value, metadata_json, metadata_value_json, player_json,
+    player_state_json,
 };
 use super::CommandName;
-use crate::core_definition::CommandExecContext;
-use mpris::{DBusError, PlayerFinder};
-use std::process;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use common::config::Config;
+use common::player::PlayerAction;
+use common::utils::{display_width, pad_string};
+use common::volume::{
+    apply_volume_change, get_muted_volume, parse_volume_change, set_muted_volume,
+};
+use mpris::{LoopStatus, PlaybackStatus};
+use serde_json::{json, Value};
+use std::io::{self, IsTerminal};
 use std::time::Duration;
 
-pub fn help_cmd(ctx: CommandExecContext<CommandName>) {
-    ctx.app.help();
+pub fn play_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "play", |player| player.play())
 }
 
-pub fn play_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "play", |player| player.play());
+pub fn pause_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "pause", |player| player.pause())
 }
 
-pub fn pause_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "pause", |player| player.pause());
+pub fn raise_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "raise", |player| player.raise())
 }
 
-pub fn raise_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "raise", |player| player.raise());
+pub fn play_pause_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "play/pause", |player| player.play_pause())
 }
 
-pub fn play_pause_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "play/pause", |player| player.play_pause());
+pub fn stop_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "stop", |player| player.stop())
 }
 
-pub fn stop_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "stop", |player| player.stop());
+pub fn next_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "next", |player| player.next())
 }
 
-pub fn next_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "next", |player| player.next());
+pub fn previous_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    exec_player_action(&ctx, "previous", |player| player.previous())
 }
 
-pub fn previous_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action(&ctx, "previous", |player| player.previous());
+fn metadata_output(
+    player: Value,
+    metadata: &mpris::Metadata,
+    metadata_key: Option<&String>,
+) -> CommandOutput {
+    match metadata_key {
+        Some(metadata_key) => {
+            let value = metadata.get(metadata_key);
+            CommandOutput::new(
+                value.map(format_metadata_value).unwrap_or_default(),
+                json!({
+                    "player": player,
+                    "key": metadata_key,
+                    "value": value.map(metadata_value_json),
+                }),
+            )
+        }
+        None => CommandOutput::new(
+            format_metadata_block(metadata),
+            json!({ "player": player, "metadata": metadata_json(metadata) }),
+        ),
+    }
 }
 
-pub fn metadata_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action_silent(&ctx, "metadata", |player| {
-        let metadata_key = ctx.args.get(2);
+pub fn metadata_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let metadata_key = ctx.args.get(2);
 
+    if should_follow(&ctx, None)? {
+        return follow(&ctx, |_, state| {
+            metadata_output(player_state_json(state), &state.metadata, metadata_key)
+        });
+    }
+
+    exec_player_action_silent(&ctx, "metadata", |player| {
         let metadata = player.get_metadata()?;
 
-        match metadata_key {
-            Some(metadata_key) => match metadata.get(metadata_key) {
-                Some(value) => println!("{}", format_metadata_value(value)),
-                None => eprintln!("Metadata key not found"),
-            },
-            None => {
-                for (key, value) in metadata {
-                    println!("{}: {}", key, format_metadata_value(&value));
-                }
+        if let Some(metadata_key) = metadata_key {
+            if metadata.get(metadata_key).is_none() {
+                return fail(ErrorCode::InvalidArgument, "Metadata key not found");
             }
         }
 
-        Ok(())
-    });
+        Ok(metadata_output(
+            player_json(player),
+            &metadata,
+            metadata_key,
+        ))
+    })
 }
 
-pub fn position_cmd(ctx: CommandExecContext<CommandName>) {
+fn position_output(player: Value, position: Duration) -> CommandOutput {
+    CommandOutput::new(
+        position.as_millis().to_string(),
+        json!({ "player": player, "position": position.as_millis() as u64 }),
+    )
+}
+
+pub fn position_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
     let value = ctx.args.get(2);
 
-    exec_player_action_silent(&ctx, "position", |player| -> Result<(), DBusError> {
-        let metadata = player.get_metadata()?;
+    if should_follow(&ctx, value)? {
+        return follow(&ctx, |player, state| {
+            let position = player.get_position().unwrap_or_default();
+            position_output(player_state_json(state), position)
+        });
+    }
 
-        let track_id = metadata.track_id().expect("No track id");
+    match value {
+        // a bare number is in milliseconds, like it always was
+        Some(value) => seek(&ctx, value, Duration::from_millis(1)),
+        None => exec_player_action_silent(&ctx, "position", |player| {
+            Ok(position_output(player_json(player), player.get_position()?))
+        }),
+    }
+}
 
-        match value {
-            Some(value) => {
-                let offset = match value.chars().last().expect("Invalid position") {
-                    '+' => parse_offset(value).expect("Invalid position"),
-                    '-' => -parse_offset(value).expect("Invalid position"),
-                    _ => {
-                        let value = value.parse::<u64>().expect("Invalid position");
-                        return player.set_position(track_id, &Duration::from_millis(value));
-                    }
-                };
+// the format to draw the art with, if it was asked for and the output isn't JSON
+fn art_format(ctx: &CommandExecContext<CommandName>) -> Result<Option<ArtFormat>, CommandError> {
+    let flags = &ctx.args.flags;
+    if wants_json(ctx) || !(flags.contains_key("art") || flags.contains_key("art-format")) {
+        return Ok(None);
+    }
 
-                let duration = player
-                    .get_position()?
-                    .checked_add(Duration::from_millis(offset as u64))
-                    .expect("Invalid position");
+    let format = match flags.get("art-format") {
+        Some(format) => match format.parse() {
+            Ok(format) => format,
+            Err(err) => return fail(ErrorCode::InvalidArgument, err),
+        },
+        None => ArtFormat::detect(),
+    };
+
+    // the escapes would end up in a file or another program
+    if !io::stdout().is_terminal() {
+        eprintln!("The album art is only shown in a terminal");
+        return Ok(None);
+    }
 
-                player.set_position(track_id, &duration)?
-            }
-            None => println!("{}", player.get_position()?.as_millis()),
-        }
-        Ok(())
-    });
+    Ok(Some(format))
 }
 
-pub fn show_cmd(ctx: CommandExecContext<CommandName>) {
+pub fn show_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let art_format = art_format(&ctx)?;
+
     exec_player_action_silent(&ctx, "show", |player| {
-        println!("{} ({})", player.identity(), player.bus_name());
-        println!("Playback status: {:?}", player.get_playback_status()?);
-        print_if_ok("Volume", player.get_volume());
-        print_if_ok("Position", player.get_position());
+        let status = player.get_playback_status()?;
+        let volume = player.get_volume();
+        let position = player.get_position();
         let metadata = player.get_metadata()?;
 
-        println!("Metadata:");
-        for (key, value) in metadata {
-            println!("  {}: {}", key, format_metadata_value(&value));
+        let mut lines = vec![
+            format!("{} ({})", player.identity(), player.bus_name()),
+            format!("Playback status: {:?}", status),
+        ];
+        lines.extend(format_if_ok("Volume", &volume));
+        lines.extend(format_if_ok("Position", &position));
+        lines.push("Metadata:".to_string());
+        for (key, value) in metadata.clone() {
+            lines.push(format!("  {}: {}", key, format_metadata_value(&value)));
+        }
+
+        // the art is a nice extra, so the rest is shown even if it fails
+        if let Some(format) = art_format {
+            match metadata.art_url().map(|url| render_art(url, format)) {
+                Some(Ok(art)) => lines.insert(0, art),
+                Some(Err(err)) => eprintln!("Failed to show the album art: {err:#}"),
+                None => eprintln!("The track has no album art"),
+            }
         }
-        Ok(())
-    });
+
+        Ok(CommandOutput::new(
+            lines.join("\n"),
+            json!({
+                "player": player_json(player),
+                "status": format!("{:?}", status),
+                "volume": volume.ok(),
+                "position": position.ok().map(|p| p.as_millis() as u64),
+                "metadata": metadata_json(&metadata),
+            }),
+        ))
+    })
+}
+
+fn loop_output(player: Value, loop_status: LoopStatus) -> CommandOutput {
+    let loop_status = format!("{:?}", loop_status);
+    CommandOutput::new(
+        loop_status.clone(),
+        json!({ "player": player, "loop": loop_status }),
+    )
 }
 
-pub fn loop_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action_silent(&ctx, "loop", |player| {
-        let value = ctx.args.get(2);
-
-        match value {
-            Some(v) => match v.to_lowercase().as_str() {
-                "none" => player.set_loop_status(mpris::LoopStatus::None),
-                "track" => player.set_loop_status(mpris::LoopStatus::Track),
-                "playlist" => player.set_loop_status(mpris::LoopStatus::Playlist),
-                _ => panic!("Invalid loop status"),
-            },
-            None => {
-                println!("{:?}", player.get_loop_status()?);
-                Ok(())
+pub fn loop_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let value = ctx.args.get(2);
+
+    if should_follow(&ctx, value)? {
+        return follow(&ctx, |_, state| {
+            loop_output(player_state_json(state), state.loop_status)
+        });
+    }
+
+    let value = match value {
+        None => None,
+        Some(v) => Some(match v.to_lowercase().as_str() {
+            "none" => LoopStatus::None,
+            "track" => LoopStatus::Track,
+            "playlist" => LoopStatus::Playlist,
+            _ => {
+                return fail(
+                    ErrorCode::InvalidArgument,
+                    format!("Invalid loop status {v}, please use none, track or playlist"),
+                )
             }
+        }),
+    };
+
+    exec_player_action_silent(&ctx, "loop", |player| match value {
+        Some(loop_status) => {
+            player.set_loop_status(loop_status)?;
+            Ok(CommandOutput::silent(
+                json!({ "action": "loop", "player": player_json(player) }),
+            ))
         }
-    });
+        None => Ok(loop_output(player_json(player), player.get_loop_status()?)),
+    })
 }
 
-pub fn shuffle_cmd(ctx: CommandExecContext<CommandName>) {
-    exec_player_action_silent(&ctx, "shuffle", |player| {
-        let value = ctx.args.get(2);
-
-        match value {
-            Some(v) => match v.to_lowercase().as_str() {
-                "true" => player.set_shuffle(true),
-                "false" => player.set_shuffle(false),
-                _ => panic!("Invalid shuffle status"),
-            },
-            None => {
-                println!("{:?}", player.get_shuffle()?);
-                Ok(())
+fn shuffle_output(player: Value, shuffle: bool) -> CommandOutput {
+    CommandOutput::new(
+        shuffle.to_string(),
+        json!({ "player": player, "shuffle": shuffle }),
+    )
+}
+
+pub fn shuffle_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let value = ctx.args.get(2);
+
+    if should_follow(&ctx, value)? {
+        return follow(&ctx, |_, state| {
+            shuffle_output(player_state_json(state), state.shuffle)
+        });
+    }
+
+    let value = match value {
+        None => None,
+        Some(v) => Some(match v.to_lowercase().as_str() {
+            "true" => true,
+            "false" => false,
+            _ => {
+                return fail(
+                    ErrorCode::InvalidArgument,
+                    format!("Invalid shuffle status {v}, please use true or false"),
+                )
             }
+        }),
+    };
+
+    exec_player_action_silent(&ctx, "shuffle", |player| match value {
+        Some(shuffle) => {
+            player.set_shuffle(shuffle)?;
+            Ok(CommandOutput::silent(
+                json!({ "action": "shuffle", "player": player_json(player) }),
+            ))
         }
-    });
+        None => Ok(shuffle_output(player_json(player), player.get_shuffle()?)),
+    })
 }
 
-pub fn scroll_preferred_player_cmd(ctx: CommandExecContext<CommandName>) {
+pub fn scroll_preferred_player_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
     let direction = ctx.args.get(2);
 
     match direction.map(String::as_str) {
-        None => {
-            eprintln!("Direction not provided, please use up or down");
-            process::exit(1);
-        }
+        None => fail(
+            ErrorCode::InvalidArgument,
+            "Direction not provided, please use up or down",
+        ),
         Some("up") | Some("down") => {
-            let current_player_name = common::player::get_preferred_player_name()
-                .expect("Failed to get current preferred player");
-
-            let current_player_name = match current_player_name {
-                Some(current_player_name) => current_player_name,
-                None => {
-                    if let Some(first_player) =
-                        common::player::get_first_player().expect("Failed to get current players")
-                    {
-                        String::from(first_player.bus_name())
-                    } else {
-                        eprintln!("No players running");
-                        process::exit(1);
-                    }
-                }
-            };
+            let current_player_name =
+                common::player::get_preferred_player_name().map_err(|err| {
+                    CommandError::new(
+                        ErrorCode::Failed,
+                        format!("Failed to get current preferred player: {err}"),
+                    )
+                })?;
+
+            let players = common::player::find_all_players().map_err(|err| {
+                CommandError::new(ErrorCode::DBus, format!("Failed to list players: {err}"))
+            })?;
+
+            if players.is_empty() {
+                return fail(ErrorCode::NoPlayer, "No players running");
+            }
 
-            let finder = PlayerFinder::new().expect("Failed to start player finder");
-            let players = finder.find_all().expect("Failed to list players");
-            let current_index = players
-                .iter()
-                .position(|p| p.bus_name() == current_player_name)
+            let current_index = current_player_name
+                .and_then(|name| players.iter().position(|p| p.bus_name() == name))
                 .unwrap_or(0);
 
-            let new_index = match direction.map(String::as_str).unwrap() {
-                "up" => {
+            let new_index = match direction.map(String::as_str) {
+                Some("up") => {
                     if current_index == 0 {
                         players.len() - 1
                     } else {
                         current_index - 1
                     }
                 }
-                "down" => (current_index + 1) % players.len(),
-                _ => unreachable!(),
+                _ => (current_index + 1) % players.len(),
             };
 
-            let player_name = players[new_index].bus_name();
-
-            common::player::set_preferred_player_name(player_name)
-                .expect("Failed to set preferred player name");
-            println!("Preferred player set to {}", player_name);
-        }
-        Some(invalid) => {
-            eprintln!("Direction {invalid} is invalid, please use up or down");
-            process::exit(1);
+            set_preferred_player(&ctx, players[new_index].bus_name())
         }
+        Some(invalid) => fail(
+            ErrorCode::InvalidArgument,
+            format!("Direction {invalid} is invalid, please use up or down"),
+        ),
     }
 }
 
-pub fn set_preferred_player_cmd(ctx: CommandExecContext<CommandName>) {
-    let player_name = ctx.args.get(2);
-
-    match player_name {
-        None => {
-            eprintln!("Player name not provided");
-            process::exit(1);
-        }
+pub fn set_preferred_player_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    match ctx.args.get(2) {
+        None => fail(ErrorCode::InvalidArgument, "Player name not provided"),
         Some(player_name) => {
-            common::player::set_preferred_player_name(player_name)
-                .expect("Failed to set preferred player name");
-            println!("Preferred player set to {}", player_name);
+            let player = find_player(player_name)?;
+            set_preferred_player(&ctx, player.bus_name())
         }
     }
 }
 
-pub fn volume_cmd(ctx: CommandExecContext<CommandName>) {
+fn set_preferred_player(ctx: &CommandExecContext<CommandName>, player_name: &str) -> CommandResult {
+    common::player::set_preferred_player_name(player_name).map_err(|err| {
+        CommandError::new(
+            ErrorCode::Failed,
+            format!("Failed to set preferred player name: {err}"),
+        )
+    })?;
+
+    print_output(
+        ctx,
+        CommandOutput::new(
+            format!("Preferred player set to {}", player_name),
+            json!({ "preferred": player_name }),
+        ),
+    );
+    Ok(())
+}
+
+fn volume_output(player: Value, volume: f64) -> CommandOutput {
+    CommandOutput::new(
+        volume.to_string(),
+        json!({ "player": player, "volume": volume }),
+    )
+}
+
+pub fn volume_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
     let value = ctx.args.get(2);
 
-    exec_player_action_silent(&ctx, "volume", |player| -> Result<(), DBusError> {
-        match value {
-            Some(value) => {
-                let offset = match value.chars().last().expect("Invalid volume") {
-                    '+' => parse_offset(value).expect("Invalid volume"),
-                    '-' => -parse_offset(value).expect("Invalid volume"),
-                    _ => {
-                        return player.set_volume(value.parse::<f64>().expect("Invalid volume"));
-                    }
-                };
+    if should_follow(&ctx, value)? {
+        return follow(&ctx, |_, state| {
+            volume_output(player_state_json(state), state.volume)
+        });
+    }
 
-                player.set_volume(player.get_volume()? + offset)?;
-            }
-            None => println!("{}", player.get_volume()?),
-        }
-        Ok(())
-    });
+    let change = value
+        .map(|value| {
+            parse_volume_change(value).map_err(|e| {
+                CommandError::new(ErrorCode::InvalidArgument, format!("Invalid volume: {e}"))
+            })
+        })
+        .transpose()?;
+
+    // only the limits are needed, and only when changing it
+    let config = match change {
+        Some(_) => common::config::load_config().map_err(|err| {
+            CommandError::new(ErrorCode::Failed, format!("Failed to load config: {err}"))
+        })?,
+        None => Config::default(),
+    };
+
+    exec_player_action_silent(&ctx, "volume", |player| {
+        let change = match change {
+            Some(change) => change,
+            None => return Ok(volume_output(player_json(player), player.get_volume()?)),
+        };
+
+        let muted = get_muted_volume(player.bus_name()).map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to get the muted volume: {err}"),
+            )
+        })?;
+
+        let (volume, muted) =
+            apply_volume_change(change, player.get_volume()?, muted, &config.volume);
+
+        PlayerAction::Volume(volume).apply(player)?;
+
+        set_muted_volume(player.bus_name(), muted).map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to save the muted volume: {err}"),
+            )
+        })?;
+
+        Ok(CommandOutput::silent(json!({
+            "action": "volume",
+            "player": player_json(player),
+            "volume": volume,
+            "muted": muted.is_some(),
+        })))
+    })
 }
 
-pub fn status_cmd(ctx: CommandExecContext<CommandName>) {
+fn status_output(player: Value, status: PlaybackStatus) -> CommandOutput {
+    let status = format!("{:?}", status);
+    CommandOutput::new(
+        status.clone(),
+        json!({ "player": player, "status": status }),
+    )
+}
+
+pub fn status_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    if should_follow(&ctx, None)? {
+        return follow(&ctx, |_, state| {
+            status_output(player_state_json(state), state.playback_status)
+        });
+    }
+
     exec_player_action_silent(&ctx, "status", |player| {
-        let status = player.get_playback_status()?;
-        println!("{:?}", status);
-        Ok(())
-    });
+        Ok(status_output(
+            player_json(player),
+            player.get_playback_status()?,
+        ))
+    })
 }
 
-pub fn list_players_cmd(_: CommandExecContext<CommandName>) {
+pub fn list_players_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
     let preferred_player_name = common::player::get_preferred_player_name()
-        .expect("Failed to get preferred player name")
+        .map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to get preferred player name: {err}"),
+            )
+        })?
         .unwrap_or("".into());
 
-    let players = PlayerFinder::new()
-        .expect("Failed create PlayerFinder")
-        .find_all()
-        .expect("Failed to list players");
+    let players = common::player::find_all_players().map_err(|err| {
+        CommandError::new(ErrorCode::DBus, format!("Failed to list players: {err}"))
+    })?;
+
+    let players_json = players
+        .iter()
+        .map(|player| {
+            json!({
+                "name": player.bus_name(),
+                "identity": player.identity(),
+                "preferred": player.bus_name() == preferred_player_name,
+            })
+        })
+        .collect::<Vec<_>>();
 
     if players.is_empty() {
-        println!("No players found");
-        return;
+        print_output(
+            &ctx,
+            CommandOutput::new("No players found", json!({ "players": players_json })),
+        );
+        return Ok(());
     }
 
-    for player in players {
-        if player.bus_name() == preferred_player_name {
-            println!("{}: {} (preferred)", player.identity(), player.bus_name());
-        } else {
-            println!("{}: {}", player.identity(), player.bus_name());
-        }
-    }
+    let identity_width = players
+        .iter()
+        .map(|p| display_width(p.identity()))
+        .max()
+        .unwrap_or(0);
+
+    let lines = players
+        .iter()
+        .map(|player| {
+            let identity = pad_string(&format!("{}:", player.identity()), identity_width + 1);
+
+            if player.bus_name() == preferred_player_name {
+                format!("{} {} (preferred)", identity, player.bus_name())
+            } else {
+                format!("{} {}", identity, player.bus_name())
+            }
+        })
+        .collect::<Vec<_>>();
+
+    print_output(
+        &ctx,
+        CommandOutput::new(lines.join("\n"), json!({ "players": players_json })),
+    );
+    Ok(())
 }
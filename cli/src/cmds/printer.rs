@@ -0,0 +1,56 @@
+use super::output::print_line;
+
+/// Print lines to stdout, skipping the ones identical to the previous one
+pub struct Printer {
+    last_line: Option<String>,
+    // where the lines go, stdout except in the tests
+    output: Box<dyn FnMut(&str) + Send>,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Printer {
+            last_line: None,
+            output: Box::new(|line| print_line(line)),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_output(output: impl FnMut(&str) + Send + 'static) -> Self {
+        Printer {
+            last_line: None,
+            output: Box::new(output),
+        }
+    }
+
+    pub fn print(&mut self, line: String) {
+        if self.should_print(&line) {
+            (self.output)(&line);
+            self.last_line = Some(line);
+        }
+    }
+
+    fn should_print(&self, line: &str) -> bool {
+        self.last_line.as_deref() != Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_skip_duplicates() {
+        let printed = Arc::new(Mutex::new(vec![]));
+        let output = printed.clone();
+        let mut printer =
+            Printer::with_output(move |line| output.lock().unwrap().push(line.to_string()));
+
+        for line in ["a", "a", "b", "a", "a"] {
+            printer.print(line.to_string());
+        }
+
+        assert_eq!(*printed.lock().unwrap(), vec!["a", "b", "a"]);
+    }
+}
@@ -0,0 +1,296 @@
+use super::output::{fail, CommandError, CommandOutput, ErrorCode};
+use super::utils::{exec_player_action_silent, player_json};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use common::player::PlayerAction;
+use common::utils::format_duration;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// A point of the track, as a time or a percentage of its length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekPosition {
+    Time(Duration),
+    Percent(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekTarget {
+    Absolute(SeekPosition),
+    Forward(SeekPosition),
+    Backward(SeekPosition),
+}
+
+impl SeekTarget {
+    /// The new position, clamped between the start and the end of the track
+    pub fn resolve(&self, current: Duration, length: Option<Duration>) -> Result<Duration, String> {
+        let to_duration = |position: &SeekPosition| match position {
+            SeekPosition::Time(time) => Ok(*time),
+            SeekPosition::Percent(percent) => length
+                .map(|length| length.mul_f64(percent / 100.0))
+                .ok_or_else(|| {
+                    String::from("The track length is unknown, so percentages can't be used")
+                }),
+        };
+
+        let position = match self {
+            SeekTarget::Absolute(position) => to_duration(position)?,
+            SeekTarget::Forward(offset) => current.saturating_add(to_duration(offset)?),
+            SeekTarget::Backward(offset) => current.saturating_sub(to_duration(offset)?),
+        };
+
+        Ok(match length {
+            Some(length) => position.min(length),
+            None => position,
+        })
+    }
+}
+
+// only digits and a dot, so "-1", "inf" and "nan" are not numbers
+fn parse_number(s: &str) -> Result<f64, String> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err(format!("{s} is not a number"));
+    }
+
+    s.parse::<f64>()
+        .map_err(|e| format!("{s} is not a number: {e}"))
+}
+
+// rounded to microseconds, the precision of MPRIS, so 5000 * 0.001 is 5s
+fn secs_to_duration(secs: f64) -> Result<Duration, String> {
+    Duration::try_from_secs_f64((secs * 1e6).round() / 1e6)
+        .map_err(|e| format!("Invalid time: {e}"))
+}
+
+/// Parse a time like `1:23`, `1:02:03`, `1h2m`, `30s` or `500ms`. A bare
+/// number is in `bare_unit`.
+pub fn parse_time(s: &str, bare_unit: Duration) -> Result<Duration, String> {
+    if s.is_empty() {
+        return Err(String::from("No time provided"));
+    }
+
+    if s.contains(':') {
+        let parts = s.split(':').collect::<Vec<_>>();
+        if parts.len() > 3 {
+            return Err(format!("Invalid time {s}, please use h:mm:ss or m:ss"));
+        }
+
+        let mut secs = 0.0;
+        for part in parts {
+            secs = secs * 60.0 + parse_number(part)?;
+        }
+        return secs_to_duration(secs);
+    }
+
+    if s.ends_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return secs_to_duration(bare_unit.as_secs_f64() * parse_number(s)?);
+    }
+
+    // a number followed by its unit, as many times as needed
+    let mut secs = 0.0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let unit_end = rest[number_end..]
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .map_or(rest.len(), |idx| number_end + idx);
+
+        // like "abc", parse_number would complain about an empty number
+        if number_end == 0 {
+            return Err(format!("{s} is not a time"));
+        }
+
+        let number = parse_number(&rest[..number_end])?;
+        let unit = match &rest[number_end..unit_end] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            unit => return Err(format!("Invalid unit {unit}, please use h, m, s or ms")),
+        };
+
+        secs += number * unit;
+        rest = &rest[unit_end..];
+    }
+
+    secs_to_duration(secs)
+}
+
+/// Parse a position like `1:23`, `50%`, `30s+` or `10s-`, see `parse_time`.
+/// The sign of an offset can also lead, as in `-10s`.
+pub fn parse_seek(arg: &str, bare_unit: Duration) -> Result<SeekTarget, String> {
+    let (value, target): (_, fn(SeekPosition) -> SeekTarget) = match (
+        arg.strip_suffix('+').or_else(|| arg.strip_prefix('+')),
+        arg.strip_suffix('-').or_else(|| arg.strip_prefix('-')),
+    ) {
+        (Some(value), _) => (value, SeekTarget::Forward),
+        (_, Some(value)) => (value, SeekTarget::Backward),
+        _ => (arg, SeekTarget::Absolute),
+    };
+
+    let position = match value.strip_suffix('%') {
+        Some(percent) => {
+            let percent = parse_number(percent)?;
+            if percent > 100.0 {
+                return Err(format!(
+                    "Invalid percentage {percent}, it can't be over 100"
+                ));
+            }
+            SeekPosition::Percent(percent)
+        }
+        None => SeekPosition::Time(parse_time(value, bare_unit)?),
+    };
+
+    Ok(target(position))
+}
+
+fn seek_output(player: Value, position: Duration, length: Option<Duration>) -> CommandOutput {
+    let text = match length {
+        Some(length) => format!(
+            "Position set to {} / {}",
+            format_duration(position),
+            format_duration(length)
+        ),
+        None => format!("Position set to {}", format_duration(position)),
+    };
+
+    CommandOutput::new(
+        text,
+        json!({
+            "action": "seek",
+            "player": player,
+            "position": position.as_millis() as u64,
+            "length": length.map(|length| length.as_millis() as u64),
+        }),
+    )
+}
+
+/// Move to `arg`, where a bare number is in `bare_unit`
+pub fn seek(
+    ctx: &CommandExecContext<CommandName>,
+    arg: &str,
+    bare_unit: Duration,
+) -> CommandResult {
+    let target = parse_seek(arg, bare_unit).map_err(|e| {
+        CommandError::new(ErrorCode::InvalidArgument, format!("Invalid position: {e}"))
+    })?;
+
+    exec_player_action_silent(ctx, "seek", |player| {
+        if !player.can_seek()? {
+            return fail(ErrorCode::Unsupported, "The player can't seek");
+        }
+
+        let metadata = player.get_metadata()?;
+        if metadata.track_id().is_none() {
+            return fail(ErrorCode::Unsupported, "The track has no id");
+        }
+        let length = metadata.length();
+
+        let current = match target {
+            SeekTarget::Absolute(_) => Duration::ZERO,
+            _ => player.get_position()?,
+        };
+
+        let position = target
+            .resolve(current, length)
+            .map_err(|e| CommandError::new(ErrorCode::Unsupported, e))?;
+
+        PlayerAction::Seek(position).apply(player)?;
+
+        Ok(seek_output(player_json(player), position, length))
+    })
+}
+
+pub fn seek_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    match ctx.args.get(2) {
+        None => fail(
+            ErrorCode::InvalidArgument,
+            "Position not provided, please use eg: 1:23, 30s+, 10s- or 50%",
+        ),
+        Some(arg) => seek(&ctx, arg, Duration::from_secs(1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("1:23", SECOND), Ok(Duration::from_secs(83)));
+        assert_eq!(parse_time("1:02:03", SECOND), Ok(Duration::from_secs(3723)));
+        assert_eq!(parse_time("1h2m", SECOND), Ok(Duration::from_secs(3720)));
+        assert_eq!(parse_time("1m30s", SECOND), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_time("500ms", SECOND), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_time("1.5s", SECOND), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_time("90", SECOND), Ok(Duration::from_secs(90)));
+        assert_eq!(
+            parse_time("5000", Duration::from_millis(1)),
+            Ok(Duration::from_secs(5))
+        );
+
+        assert!(parse_time("1d", SECOND).is_err());
+        assert!(parse_time("1:2:3:4", SECOND).is_err());
+        assert_eq!(
+            parse_time("nan", SECOND),
+            Err(String::from("nan is not a time"))
+        );
+        assert!(parse_time("", SECOND).is_err());
+    }
+
+    #[test]
+    fn test_parse_seek() {
+        let time = |secs| SeekPosition::Time(Duration::from_secs(secs));
+
+        assert_eq!(
+            parse_seek("30s+", SECOND),
+            Ok(SeekTarget::Forward(time(30)))
+        );
+        assert_eq!(
+            parse_seek("10s-", SECOND),
+            Ok(SeekTarget::Backward(time(10)))
+        );
+        assert_eq!(
+            parse_seek("50%", SECOND),
+            Ok(SeekTarget::Absolute(SeekPosition::Percent(50.0)))
+        );
+        assert!(parse_seek("150%", SECOND).is_err());
+
+        assert_eq!(
+            parse_seek("-5000", Duration::from_millis(1)),
+            Ok(SeekTarget::Backward(time(5)))
+        );
+        assert_eq!(
+            parse_seek("+1:00", SECOND),
+            Ok(SeekTarget::Forward(time(60)))
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let current = Duration::from_secs(5);
+        let length = Some(Duration::from_secs(200));
+        let time = |secs| SeekPosition::Time(Duration::from_secs(secs));
+
+        // going back past the start stops at the start
+        assert_eq!(
+            SeekTarget::Backward(time(10)).resolve(current, length),
+            Ok(Duration::ZERO)
+        );
+        assert_eq!(
+            SeekTarget::Forward(time(500)).resolve(current, length),
+            Ok(Duration::from_secs(200))
+        );
+        assert_eq!(
+            SeekTarget::Absolute(SeekPosition::Percent(50.0)).resolve(current, length),
+            Ok(Duration::from_secs(100))
+        );
+        assert!(SeekTarget::Absolute(SeekPosition::Percent(50.0))
+            .resolve(current, None)
+            .is_err());
+    }
+}
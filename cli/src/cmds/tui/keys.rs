@@ -0,0 +1,83 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TuiAction {
+    Quit,
+    PlayPause,
+    Next,
+    Previous,
+    SeekForward,
+    SeekBackward,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    CycleLoop,
+    ToggleShuffle,
+    SelectNext,
+    SelectPrevious,
+    /// Make the selected player the preferred one
+    SwitchPlayer,
+}
+
+/// The keys and what they do, for the help at the bottom
+pub const KEYS: [(&str, &str); 10] = [
+    ("space", "play/pause"),
+    ("n/p", "next/previous"),
+    ("←/→", "seek"),
+    ("↑/↓", "volume"),
+    ("m", "mute"),
+    ("l", "loop"),
+    ("s", "shuffle"),
+    ("j/k", "select player"),
+    ("enter", "switch player"),
+    ("q", "quit"),
+];
+
+pub fn key_action(key: KeyEvent) -> Option<TuiAction> {
+    // raw mode eats ctrl-c, so it's handled here
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return match key.code {
+            KeyCode::Char('c') => Some(TuiAction::Quit),
+            _ => None,
+        };
+    }
+
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => Some(TuiAction::Quit),
+        KeyCode::Char(' ') => Some(TuiAction::PlayPause),
+        KeyCode::Char('n') => Some(TuiAction::Next),
+        KeyCode::Char('p') => Some(TuiAction::Previous),
+        KeyCode::Right => Some(TuiAction::SeekForward),
+        KeyCode::Left => Some(TuiAction::SeekBackward),
+        KeyCode::Up | KeyCode::Char('+') => Some(TuiAction::VolumeUp),
+        KeyCode::Down | KeyCode::Char('-') => Some(TuiAction::VolumeDown),
+        KeyCode::Char('m') => Some(TuiAction::ToggleMute),
+        KeyCode::Char('l') => Some(TuiAction::CycleLoop),
+        KeyCode::Char('s') => Some(TuiAction::ToggleShuffle),
+        KeyCode::Char('j') | KeyCode::Tab => Some(TuiAction::SelectNext),
+        KeyCode::Char('k') | KeyCode::BackTab => Some(TuiAction::SelectPrevious),
+        KeyCode::Enter => Some(TuiAction::SwitchPlayer),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_action() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert_eq!(
+            key_action(key(KeyCode::Char(' '))),
+            Some(TuiAction::PlayPause)
+        );
+        assert_eq!(key_action(key(KeyCode::Up)), Some(TuiAction::VolumeUp));
+        assert_eq!(key_action(key(KeyCode::Char('x'))), None);
+        assert_eq!(
+            key_action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(TuiAction::Quit)
+        );
+    }
+}
@@ -0,0 +1,469 @@
+mod keys;
+mod view;
+
+use super::broadcast::is_broadcast;
+use super::output::{fail, CommandError, ErrorCode};
+use super::seek::{SeekPosition, SeekTarget};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use anyhow::Result as AnyResult;
+use common::config::VolumeConfig;
+use common::player::{MprisWrapper, PlayerAction, PlayerState, PlayersEvent};
+use common::volume::{apply_volume_change, get_muted_volume, set_muted_volume, VolumeChange};
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue, style};
+use keys::{key_action, TuiAction};
+use mpris::LoopStatus;
+use std::io::{self, IsTerminal, Write};
+use std::sync::mpsc as std_mpsc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::MissedTickBehavior;
+use view::View;
+
+// the position is not sent by the listener, so it's queried this often
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+pub fn tui_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    if is_broadcast(&ctx) {
+        return fail(
+            ErrorCode::InvalidArgument,
+            "The TUI can't be used with --all-players or --players",
+        );
+    }
+
+    if !io::stdout().is_terminal() {
+        return fail(ErrorCode::InvalidArgument, "The TUI needs a terminal");
+    }
+
+    let config = common::config::load_config().map_err(|err| {
+        CommandError::new(ErrorCode::Failed, format!("Failed to load config: {err:#}"))
+    })?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to start the runtime: {err}"),
+            )
+        })?;
+
+    let screen = Screen::enter().map_err(|err| {
+        CommandError::new(
+            ErrorCode::Failed,
+            format!("Failed to set up the terminal: {err}"),
+        )
+    })?;
+
+    let fixed_player = ctx.args.flags.get("player").cloned();
+    let result = runtime.block_on(tui_loop(&screen, fixed_player, config.volume));
+
+    // the terminal must be back to normal before printing anything
+    drop(screen);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => fail(ErrorCode::Failed, format!("Failed to run the TUI: {err:#}")),
+    }
+}
+
+/// Raw mode in the alternate screen, until it's dropped
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Screen)
+    }
+
+    fn draw(&self, lines: &[String]) -> io::Result<()> {
+        let mut stdout = io::stdout();
+
+        for (row, line) in lines.iter().enumerate() {
+            queue!(
+                stdout,
+                cursor::MoveTo(0, row as u16),
+                style::Print(line),
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+        }
+        queue!(stdout, terminal::Clear(ClearType::FromCursorDown))?;
+
+        stdout.flush()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The player being controlled
+struct Current {
+    player: mpris::Player,
+    state: Option<PlayerState>,
+    position: Duration,
+    events: Receiver<PlayerState>,
+    actions: std_mpsc::Sender<PlayerAction>,
+}
+
+impl Current {
+    fn connect(player: mpris::Player) -> AnyResult<Self> {
+        let wrapper = MprisWrapper::new(player.bus_name().to_string());
+
+        let (event_tx, events) = mpsc::channel(1);
+        let (actions, action_rx) = std_mpsc::channel();
+        wrapper.start_listener(event_tx)?;
+        wrapper.start_controller(action_rx)?;
+
+        Ok(Current {
+            position: player.get_position().unwrap_or_default(),
+            player,
+            state: None,
+            events,
+            actions,
+        })
+    }
+}
+
+struct Tui {
+    fixed_player: Option<String>,
+    volume_config: VolumeConfig,
+    current: Option<Current>,
+    /// Every player, sorted by bus name
+    players: Vec<PlayerState>,
+    /// The bus name of the selected player, the current one if none
+    selected: Option<String>,
+    message: Option<String>,
+}
+
+impl Tui {
+    fn new(fixed_player: Option<String>, volume_config: VolumeConfig) -> Self {
+        Tui {
+            fixed_player,
+            volume_config,
+            current: None,
+            players: vec![],
+            selected: None,
+            message: None,
+        }
+    }
+
+    fn current_name(&self) -> Option<&str> {
+        self.current
+            .as_ref()
+            .map(|current| current.player.bus_name())
+    }
+
+    fn switch_to(&mut self, player: mpris::Player) {
+        match Current::connect(player) {
+            Ok(current) => self.current = Some(current),
+            Err(err) => self.message = Some(format!("Failed to listen to the player: {err}")),
+        }
+    }
+
+    // look for a player when there's none, or refresh the position
+    fn tick(&mut self) {
+        if let Some(current) = &mut self.current {
+            if let Ok(position) = current.player.get_position() {
+                current.position = position;
+            }
+            return;
+        }
+
+        let player = match &self.fixed_player {
+            Some(name) => common::player::find_player(name),
+            None => common::player::get_preferred_player_or_first(),
+        };
+
+        match player {
+            Ok(Some(player)) => self.switch_to(player),
+            // wait for it to show up
+            Ok(None) => {}
+            Err(err) => self.message = Some(format!("Failed to get player: {err}")),
+        }
+    }
+
+    fn preferred_changed(&mut self, name: &str) {
+        if name.is_empty() || self.current_name() == Some(name) {
+            return;
+        }
+
+        match common::player::get_player_by_bus_name(name) {
+            Ok(Some(player)) => self.switch_to(player),
+            Ok(None) => {}
+            Err(err) => self.message = Some(format!("Failed to get player: {err}")),
+        }
+    }
+
+    fn update_players(&mut self, event: PlayersEvent) {
+        match event {
+            PlayersEvent::Changed(state) => {
+                match self.players.binary_search_by(|p| p.name.cmp(&state.name)) {
+                    Ok(idx) => self.players[idx] = state,
+                    Err(idx) => self.players.insert(idx, state),
+                }
+            }
+            PlayersEvent::Vanished(name) => self.players.retain(|p| p.name != name),
+        }
+    }
+
+    // the index of the selected player in the list
+    fn selected_index(&self) -> usize {
+        let selected = self.selected.as_deref().or(self.current_name());
+
+        self.players
+            .iter()
+            .position(|player| Some(player.name.as_str()) == selected)
+            .unwrap_or(0)
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.selected = self.players.get(idx).map(|player| player.name.clone());
+    }
+
+    fn run(&mut self, action: TuiAction) {
+        self.message = None;
+
+        if let Err(err) = self.try_run(action) {
+            self.message = Some(format!("Failed to run {action:?}: {err}"));
+        }
+    }
+
+    fn try_run(&mut self, action: TuiAction) -> AnyResult<()> {
+        match action {
+            TuiAction::SelectNext => {
+                let last = self.players.len().saturating_sub(1);
+                self.select((self.selected_index() + 1).min(last));
+                return Ok(());
+            }
+            TuiAction::SelectPrevious => {
+                self.select(self.selected_index().saturating_sub(1));
+                return Ok(());
+            }
+            TuiAction::SwitchPlayer => {
+                if let Some(selected) = self.players.get(self.selected_index()) {
+                    let name = selected.name.clone();
+                    common::player::set_preferred_player_name(&name)?;
+                    self.preferred_changed(&name);
+                    self.selected = None;
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let current = match &mut self.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        let state = match &current.state {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+
+        let player_action = match action {
+            TuiAction::PlayPause => PlayerAction::PlayPause,
+            TuiAction::Next => PlayerAction::Next,
+            TuiAction::Previous => PlayerAction::Previous,
+            TuiAction::ToggleShuffle => PlayerAction::Shuffle(!state.shuffle),
+            TuiAction::CycleLoop => PlayerAction::Loop(match state.loop_status {
+                LoopStatus::None => LoopStatus::Playlist,
+                LoopStatus::Playlist => LoopStatus::Track,
+                LoopStatus::Track => LoopStatus::None,
+            }),
+            TuiAction::SeekForward | TuiAction::SeekBackward => {
+                let target = match action {
+                    TuiAction::SeekForward => SeekTarget::Forward,
+                    _ => SeekTarget::Backward,
+                };
+                let position = target(SeekPosition::Time(SEEK_STEP))
+                    .resolve(current.position, state.metadata.length())
+                    .map_err(anyhow::Error::msg)?;
+
+                // don't wait for the next tick to show it
+                current.position = position;
+                PlayerAction::Seek(position)
+            }
+            TuiAction::VolumeUp | TuiAction::VolumeDown | TuiAction::ToggleMute => {
+                let change = match action {
+                    TuiAction::VolumeUp => VolumeChange::Up,
+                    TuiAction::VolumeDown => VolumeChange::Down,
+                    _ => VolumeChange::ToggleMute,
+                };
+
+                let muted = get_muted_volume(&state.name)?;
+                let (volume, muted) =
+                    apply_volume_change(change, state.volume, muted, &self.volume_config);
+                set_muted_volume(&state.name, muted)?;
+
+                PlayerAction::Volume(volume)
+            }
+            TuiAction::Quit
+            | TuiAction::SelectNext
+            | TuiAction::SelectPrevious
+            | TuiAction::SwitchPlayer => return Ok(()),
+        };
+
+        current.actions.send(player_action)?;
+        Ok(())
+    }
+
+    fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let current = self.current.as_ref();
+
+        let view = View {
+            state: current.and_then(|current| current.state.as_ref()),
+            position: current.map(|current| current.position).unwrap_or_default(),
+            players: &self.players,
+            selected: self.selected_index(),
+            message: self.message.as_deref(),
+        };
+
+        let mut lines = view::render(&view, width);
+        lines.truncate(height);
+        lines
+    }
+}
+
+// keys are read in a thread, crossterm only has a blocking read without the
+// event-stream feature
+fn spawn_key_reader(sender: mpsc::Sender<Event>) {
+    thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if sender.blocking_send(event).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+// never resolves if there's nothing to receive from
+async fn recv_or_pending<T>(receiver: Option<&mut Receiver<T>>) -> Option<T> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn tui_loop(
+    screen: &Screen,
+    fixed_player: Option<String>,
+    volume_config: VolumeConfig,
+) -> AnyResult<()> {
+    let mut tui = Tui::new(fixed_player, volume_config);
+
+    let (key_tx, mut key_rx) = mpsc::channel(16);
+    spawn_key_reader(key_tx);
+
+    let (players_tx, mut players_rx) = mpsc::channel(16);
+    common::player::spawn_players_listener(players_tx)?;
+
+    // there's nothing to watch when the player is fixed
+    let mut listener = common::player::PreferredPlayerListener::new();
+    let mut preferred_rx = match tui.fixed_player {
+        Some(_) => None,
+        None => Some(listener.start()?),
+    };
+
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            state = recv_or_pending(tui.current.as_mut().map(|current| &mut current.events)) => {
+                match state {
+                    Some(state) => {
+                        if let Some(current) = &mut tui.current {
+                            current.state = Some(state);
+                        }
+                    }
+                    // the player is gone, the next tick looks for another one
+                    None => tui.current = None,
+                }
+            },
+            _ = ticker.tick() => tui.tick(),
+            event = key_rx.recv() => {
+                match event {
+                    Some(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                        match key_action(key) {
+                            Some(TuiAction::Quit) => return Ok(()),
+                            Some(action) => tui.run(action),
+                            None => {}
+                        }
+                    }
+                    // a resize only needs a redraw
+                    Some(_) => {}
+                    None => return Ok(()),
+                }
+            },
+            event = players_rx.recv() => {
+                if let Some(event) = event {
+                    tui.update_players(event);
+                }
+            },
+            name = recv_or_pending(preferred_rx.as_mut()) => {
+                match name {
+                    Some(Ok(name)) => tui.preferred_changed(name.trim()),
+                    Some(Err(err)) => {
+                        tui.message = Some(format!("Failed to read preferred player: {err}"));
+                    }
+                    None => preferred_rx = None,
+                }
+            },
+        }
+
+        let (width, height) = terminal::size()?;
+        screen.draw(&tui.render(width as usize, height as usize))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player_state(name: &str) -> PlayerState {
+        PlayerState {
+            identity: name.to_string(),
+            name: format!("org.mpris.MediaPlayer2.{name}"),
+            metadata: mpris::Metadata::default(),
+            playback_status: mpris::PlaybackStatus::Paused,
+            loop_status: LoopStatus::None,
+            shuffle: false,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_player_list() {
+        let mut tui = Tui::new(None, VolumeConfig::default());
+
+        for name in ["spotify", "mpv", "firefox"] {
+            tui.update_players(PlayersEvent::Changed(player_state(name)));
+        }
+        let names = tui.players.iter().map(|p| p.identity.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["firefox", "mpv", "spotify"]);
+
+        tui.run(TuiAction::SelectNext);
+        tui.run(TuiAction::SelectNext);
+        assert_eq!(tui.selected_index(), 2);
+
+        // the selection follows the player, not its place in the list
+        tui.update_players(PlayersEvent::Vanished(String::from(
+            "org.mpris.MediaPlayer2.firefox",
+        )));
+        assert_eq!(tui.selected_index(), 1);
+
+        tui.run(TuiAction::SelectNext);
+        assert_eq!(tui.selected_index(), 1);
+    }
+}
@@ -0,0 +1,210 @@
+use super::keys::KEYS;
+use common::player::PlayerState;
+use common::utils::{display_width, format_duration, pad_string, truncate_string};
+use mpris::{LoopStatus, PlaybackStatus};
+use std::time::Duration;
+
+/// Everything shown on the screen
+pub struct View<'a> {
+    /// The state of the player being controlled, if there's one
+    pub state: Option<&'a PlayerState>,
+    pub position: Duration,
+    pub players: &'a [PlayerState],
+    /// The index of the selected player in `players`
+    pub selected: usize,
+    /// The last error, or what the last key did
+    pub message: Option<&'a str>,
+}
+
+fn status_icon(status: PlaybackStatus) -> &'static str {
+    match status {
+        PlaybackStatus::Playing => "▶",
+        PlaybackStatus::Paused => "⏸",
+        PlaybackStatus::Stopped => "■",
+    }
+}
+
+fn loop_name(status: LoopStatus) -> &'static str {
+    match status {
+        LoopStatus::None => "off",
+        LoopStatus::Track => "track",
+        LoopStatus::Playlist => "playlist",
+    }
+}
+
+/// `1:23 ━━━━━━──────── 3:45`, `width` columns wide
+pub fn progress_bar(position: Duration, length: Option<Duration>, width: usize) -> String {
+    let length = match length.filter(|length| !length.is_zero()) {
+        Some(length) => length,
+        None => return format_duration(position),
+    };
+    let position = position.min(length);
+
+    let elapsed = format_duration(position);
+    let total = format_duration(length);
+    let bar_width = width.saturating_sub(elapsed.len() + total.len() + 2);
+    let filled =
+        (position.as_secs_f64() / length.as_secs_f64() * bar_width as f64).round() as usize;
+
+    format!(
+        "{} {}{} {}",
+        elapsed,
+        "━".repeat(filled),
+        "─".repeat(bar_width - filled),
+        total
+    )
+}
+
+// as many keys as fit in each line
+fn keys_lines(width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+
+    for (key, description) in KEYS {
+        let item = format!("{key} {description}");
+
+        if !line.is_empty() && display_width(&line) + 2 + display_width(&item) > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push_str("  ");
+        }
+        line.push_str(&item);
+    }
+
+    lines.push(line);
+    lines
+}
+
+fn track_lines(state: &PlayerState, position: Duration, width: usize) -> Vec<String> {
+    let metadata = &state.metadata;
+
+    let artists = metadata.artists().map(|artists| artists.join(", "));
+    let album = metadata.album_name().map(String::from);
+    let details = [artists, album]
+        .into_iter()
+        .flatten()
+        .filter(|detail| !detail.is_empty())
+        .collect::<Vec<_>>();
+
+    vec![
+        format!(
+            "{} {} ({})",
+            status_icon(state.playback_status),
+            state.identity,
+            state.name
+        ),
+        String::new(),
+        metadata.title().unwrap_or("Unknown title").to_string(),
+        details.join(" - "),
+        String::new(),
+        progress_bar(position, metadata.length(), width),
+        String::new(),
+        format!(
+            "Volume: {}%   Loop: {}   Shuffle: {}",
+            (state.volume * 100.0).round(),
+            loop_name(state.loop_status),
+            if state.shuffle { "on" } else { "off" }
+        ),
+    ]
+}
+
+fn player_lines(view: &View) -> Vec<String> {
+    if view.players.is_empty() {
+        return vec![String::from("  none")];
+    }
+
+    let current_name = view.state.map(|state| state.name.as_str());
+    let identity_width = view
+        .players
+        .iter()
+        .map(|player| display_width(&player.identity))
+        .max()
+        .unwrap_or(0);
+
+    view.players
+        .iter()
+        .enumerate()
+        .map(|(idx, player)| {
+            let cursor = if idx == view.selected { ">" } else { " " };
+            let current = if Some(player.name.as_str()) == current_name {
+                "*"
+            } else {
+                " "
+            };
+
+            format!(
+                "{}{} {} {:?}",
+                cursor,
+                current,
+                pad_string(&player.identity, identity_width),
+                player.playback_status
+            )
+        })
+        .collect()
+}
+
+/// The lines to draw, none of them wider than `width`
+pub fn render(view: &View, width: usize) -> Vec<String> {
+    let mut lines = match view.state {
+        Some(state) => track_lines(state, view.position, width),
+        None => vec![String::from("No player found")],
+    };
+
+    lines.push(String::new());
+    lines.push(String::from("Players:"));
+    lines.extend(player_lines(view));
+
+    lines.push(String::new());
+    lines.extend(keys_lines(width));
+
+    if let Some(message) = view.message {
+        lines.push(String::new());
+        lines.push(message.to_string());
+    }
+
+    lines
+        .iter()
+        .map(|line| truncate_string(line, width))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_bar() {
+        let bar = progress_bar(Duration::from_secs(30), Some(Duration::from_secs(60)), 20);
+        assert_eq!(bar, "0:30 ━━━━━───── 1:00");
+        assert_eq!(display_width(&bar), 20);
+
+        assert_eq!(progress_bar(Duration::from_secs(30), None, 20), "0:30");
+    }
+
+    #[test]
+    fn test_keys_lines() {
+        let lines = keys_lines(40);
+
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| display_width(line) <= 40));
+        assert!(lines[0].starts_with("space play/pause"));
+    }
+
+    #[test]
+    fn test_render_without_player() {
+        let view = View {
+            state: None,
+            position: Duration::ZERO,
+            players: &[],
+            selected: 0,
+            message: Some("Failed to get player"),
+        };
+
+        let lines = render(&view, 80);
+
+        assert_eq!(lines[0], "No player found");
+        assert_eq!(lines.last().unwrap(), "Failed to get player");
+    }
+}
@@ -1,7 +1,10 @@
+use super::broadcast::{broadcast, is_broadcast};
+use super::output::{print_output, CommandError, CommandOutput, ErrorCode};
 use super::CommandName;
-use crate::core_definition::CommandExecContext;
-use mpris::{DBusError, MetadataValue};
-use std::process;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use common::player::{AmbiguousPlayer, PlayerState};
+use mpris::{DBusError, FindingError, MetadataValue};
+use serde_json::{json, Value};
 
 pub fn format_metadata_value(value: &MetadataValue) -> String {
     match value {
@@ -21,60 +24,184 @@ pub fn format_metadata_value(value: &MetadataValue) -> String {
     }
 }
 
-pub fn parse_offset(arg: &str) -> Result<f64, String> {
-    let arg = &arg[..arg.len() - 1];
+/// Every metadata value, one per line, sorted by key so it's stable
+pub fn format_metadata_block(metadata: &mpris::Metadata) -> String {
+    let mut lines = metadata
+        .clone()
+        .into_iter()
+        .map(|(key, value)| format!("{}: {}", key, format_metadata_value(&value)))
+        .collect::<Vec<_>>();
+    lines.sort();
 
-    arg.parse::<f64>()
-        .map_err(|e| format!("Failed to parse offset: {}", e))
+    // a blank line between each block
+    lines.push(String::new());
+    lines.join("\n")
 }
 
-pub fn exec_player_action<F>(ctx: &CommandExecContext<CommandName>, action_name: &str, action: F)
+pub fn exec_player_action<F>(
+    ctx: &CommandExecContext<CommandName>,
+    action_name: &str,
+    action: F,
+) -> CommandResult
 where
-    F: FnOnce(&mpris::Player) -> Result<(), DBusError>,
+    F: Fn(&mpris::Player) -> Result<(), DBusError>,
 {
     exec_player_action_silent(ctx, action_name, |player| {
         action(player)?;
-        println!(
-            "Action {action_name} called on player {} ({})",
-            player.identity(),
-            player.bus_name(),
-        );
-        Ok(())
-    });
+
+        let json = json!({ "action": action_name, "player": player_json(player) });
+        // the broadcast already says which player it was called on
+        if is_broadcast(ctx) {
+            return Ok(CommandOutput::silent(json));
+        }
+
+        Ok(CommandOutput::new(
+            format!(
+                "Action {action_name} called on player {} ({})",
+                player.identity(),
+                player.bus_name(),
+            ),
+            json,
+        ))
+    })
 }
 
 pub fn exec_player_action_silent<F>(
     ctx: &CommandExecContext<CommandName>,
     action_name: &str,
     action: F,
-) where
-    F: FnOnce(&mpris::Player) -> Result<(), DBusError>,
+) -> CommandResult
+where
+    F: Fn(&mpris::Player) -> Result<CommandOutput, CommandError>,
 {
+    if is_broadcast(ctx) {
+        return broadcast(ctx, action_name, action);
+    }
+
     let player = match ctx.args.flags.get("player") {
-        None => {
-            common::player::get_preferred_player_or_first().expect("Failed to get preferred player")
-        }
-        Some(player_name) => {
-            common::player::get_player_by_bus_name(player_name).expect("Failed to get player")
-        }
+        None => common::player::get_preferred_player_or_first()
+            .map_err(|err| lookup_error("Failed to get player", err))?
+            .ok_or_else(|| CommandError::new(ErrorCode::NoPlayer, "No player found"))?,
+        Some(player_name) => find_player(player_name)?,
     };
 
-    match player {
-        Some(player) => {
-            action(&player).unwrap_or_else(|_| panic!("Failed to call action {action_name}"))
-        }
-        None => {
-            eprintln!("No player found");
-            process::exit(1);
-        }
+    let output = action(&player).map_err(|err| action_error(action_name, err))?;
+    print_output(ctx, output);
+    Ok(())
+}
+
+/// The D-Bus errors don't say what was being done, so the action is added to
+/// them
+pub fn action_error(action_name: &str, err: CommandError) -> CommandError {
+    match err.code {
+        ErrorCode::DBus => CommandError::new(
+            ErrorCode::DBus,
+            format!("Failed to call action {action_name}: {}", err.message),
+        ),
+        _ => err,
+    }
+}
+
+/// An error from looking players up. Only D-Bus failures are `DBus`, a
+/// broken config or preferred player file is `Failed`.
+pub fn lookup_error(message: &str, err: anyhow::Error) -> CommandError {
+    if let Some(ambiguous) = err.downcast_ref::<AmbiguousPlayer>() {
+        return CommandError::new(ErrorCode::InvalidArgument, ambiguous);
     }
+
+    if err.is::<DBusError>() || err.is::<FindingError>() {
+        CommandError::new(ErrorCode::DBus, format!("{message}: {err}"))
+    } else {
+        // with the cause, like the line of the config that is broken
+        CommandError::new(ErrorCode::Failed, format!("{message}: {err:#}"))
+    }
+}
+
+/// The player called `name`, by bus name, identity, glob or alias
+pub fn find_player(name: &str) -> Result<mpris::Player, CommandError> {
+    common::player::find_player(name)
+        .map_err(|err| lookup_error("Failed to get player", err))?
+        .ok_or_else(|| {
+            CommandError::new(
+                ErrorCode::PlayerNotFound,
+                format!("Player {name} not found"),
+            )
+        })
+}
+
+/// The player, as it's shown in every JSON output
+pub fn player_json(player: &mpris::Player) -> Value {
+    json!({ "name": player.bus_name(), "identity": player.identity() })
+}
+
+pub fn player_state_json(state: &PlayerState) -> Value {
+    json!({ "name": state.name, "identity": state.identity })
+}
+
+pub fn metadata_json(metadata: &mpris::Metadata) -> Value {
+    Value::Object(
+        metadata
+            .clone()
+            .into_iter()
+            .map(|(key, value)| (key, metadata_value_json(&value)))
+            .collect(),
+    )
 }
 
-pub fn print_if_ok<T>(key: &str, value: Result<T, DBusError>)
+pub fn metadata_value_json(value: &MetadataValue) -> Value {
+    match value {
+        MetadataValue::String(v) => json!(v),
+        MetadataValue::I16(v) => json!(v),
+        MetadataValue::I32(v) => json!(v),
+        MetadataValue::I64(v) => json!(v),
+        MetadataValue::U8(v) => json!(v),
+        MetadataValue::U16(v) => json!(v),
+        MetadataValue::U32(v) => json!(v),
+        MetadataValue::U64(v) => json!(v),
+        MetadataValue::F64(v) => json!(v),
+        MetadataValue::Bool(v) => json!(v),
+        MetadataValue::Array(v) => Value::Array(v.iter().map(metadata_value_json).collect()),
+        MetadataValue::Map(v) => Value::Object(
+            v.iter()
+                .map(|(key, value)| (key.clone(), metadata_value_json(value)))
+                .collect(),
+        ),
+        MetadataValue::Unsupported => Value::Null,
+    }
+}
+
+pub fn format_if_ok<T>(key: &str, value: &Result<T, DBusError>) -> Option<String>
 where
     T: std::fmt::Debug,
 {
-    if let Ok(value) = value {
-        println!("{}: {:?}", key, value);
+    value
+        .as_ref()
+        .ok()
+        .map(|value| format!("{}: {:?}", key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_format_metadata_block() {
+        let metadata = mpris::Metadata::from(HashMap::from([
+            (
+                String::from("xesam:title"),
+                MetadataValue::String(String::from("Song")),
+            ),
+            (
+                String::from("xesam:album"),
+                MetadataValue::String(String::from("Album")),
+            ),
+        ]));
+
+        // blocks printed one after the other are split by a blank line
+        assert_eq!(
+            format_metadata_block(&metadata),
+            "xesam:album: Album\nxesam:title: Song\n"
+        );
     }
 }
@@ -0,0 +1,516 @@
+use super::broadcast::is_broadcast;
+use super::output::{fail, print_output, CommandError, CommandOutput, ErrorCode};
+use super::seek::parse_time;
+use super::utils::{lookup_error, player_state_json};
+use super::CommandName;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use common::player::{resolve_alias, select_player, PlayerState, PlayersEvent};
+use common::utils::format_duration;
+use mpris::{PlaybackStatus, TrackID};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+// a bare number is in seconds, for the timeout and the position
+const SECOND: Duration = Duration::from_secs(1);
+// so the position is a bit past the target when it's checked again
+const POSITION_MARGIN: Duration = Duration::from_millis(10);
+// seeks don't reach the listener, so the position is queried at least this often
+const POSITION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the `wait` command waits for
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Status(PlaybackStatus),
+    /// The track is not the one playing when the wait started
+    TrackChange,
+    /// A player matching the pattern is running
+    Player(String),
+    PositionOver(Duration),
+    PositionUnder(Duration),
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "track-change" {
+            return Ok(Condition::TrackChange);
+        }
+
+        if let Some(status) = s.strip_prefix("status=") {
+            return match status.to_lowercase().as_str() {
+                "playing" => Ok(Condition::Status(PlaybackStatus::Playing)),
+                "paused" => Ok(Condition::Status(PlaybackStatus::Paused)),
+                "stopped" => Ok(Condition::Status(PlaybackStatus::Stopped)),
+                _ => Err(format!(
+                    "Invalid status {status}, please use playing, paused or stopped"
+                )),
+            };
+        }
+
+        if let Some(player) = s.strip_prefix("player=") {
+            if player.is_empty() {
+                return Err(String::from("No player provided"));
+            }
+            return Ok(Condition::Player(player.to_string()));
+        }
+
+        if let Some(time) = s.strip_prefix("position>") {
+            return parse_time(time, SECOND).map(Condition::PositionOver);
+        }
+
+        if let Some(time) = s.strip_prefix("position<") {
+            return parse_time(time, SECOND).map(Condition::PositionUnder);
+        }
+
+        Err(format!(
+            "Invalid condition {s}, please use status=<status>, track-change, player=<player>, position>time or position<time"
+        ))
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Status(status) => write!(f, "status={:?}", status),
+            Condition::TrackChange => write!(f, "track-change"),
+            Condition::Player(player) => write!(f, "player={}", player),
+            Condition::PositionOver(time) => write!(f, "position>{}", format_duration(*time)),
+            Condition::PositionUnder(time) => write!(f, "position<{}", format_duration(*time)),
+        }
+    }
+}
+
+/// What's left to do after a player changed
+#[derive(Debug, PartialEq)]
+enum Progress {
+    Met,
+    NotYet,
+    /// The position isn't sent by the listener, so it has to be queried
+    QueryPosition,
+}
+
+/// When to look at the position again
+#[derive(Debug, PartialEq)]
+enum PositionCheck {
+    Met,
+    /// Once it's playing for that long, it should be there
+    In(Duration),
+    /// Only a seek, or a new track, can get it there
+    AfterSeek,
+    /// The player went away, it's checked again if it comes back
+    Gone,
+}
+
+// the rate is 0 when it's not playing
+fn check_position(condition: &Condition, position: Duration, rate: f64) -> PositionCheck {
+    match condition {
+        Condition::PositionOver(target) if position > *target => PositionCheck::Met,
+        // a rate so low that the wait doesn't fit in a Duration is as good as
+        // paused
+        Condition::PositionOver(target) if rate > 0.0 => {
+            match Duration::try_from_secs_f64((*target - position).as_secs_f64() / rate) {
+                Ok(duration) => PositionCheck::In(duration.saturating_add(POSITION_MARGIN)),
+                Err(_) => PositionCheck::AfterSeek,
+            }
+        }
+        Condition::PositionUnder(target) if position < *target => PositionCheck::Met,
+        _ => PositionCheck::AfterSeek,
+    }
+}
+
+// what tells two tracks apart, not every player sets the track id
+type TrackKey = (Option<TrackID>, Option<String>, Option<String>);
+
+fn track_key(metadata: &mpris::Metadata) -> TrackKey {
+    (
+        metadata.track_id(),
+        metadata.title().map(str::to_string),
+        metadata.url().map(str::to_string),
+    )
+}
+
+// the same way --player picks a player, but for a single one
+fn is_player(state: &PlayerState, pattern: &str) -> bool {
+    matches!(
+        select_player(pattern, &[(&state.name, &state.identity)]),
+        Ok(Some(_))
+    )
+}
+
+/// Keeps track of the players while waiting for the condition
+struct Waiter {
+    condition: Condition,
+    /// The pattern from --player, every player matching it is watched
+    fixed_player: Option<String>,
+    /// Otherwise the preferred player, or the first one to show up
+    target: Option<String>,
+    /// The track of each watched player when it was first seen
+    first_tracks: HashMap<String, TrackKey>,
+}
+
+impl Waiter {
+    fn new(condition: Condition, fixed_player: Option<String>, target: Option<String>) -> Self {
+        Waiter {
+            condition,
+            fixed_player,
+            target,
+            first_tracks: HashMap::new(),
+        }
+    }
+
+    fn is_watched(&mut self, state: &PlayerState) -> bool {
+        match (&self.fixed_player, &self.target) {
+            (Some(pattern), _) => is_player(state, pattern),
+            (None, Some(target)) => state.name == *target,
+            (None, None) => {
+                self.target = Some(state.name.clone());
+                true
+            }
+        }
+    }
+
+    fn handle_state(&mut self, state: &PlayerState) -> Progress {
+        if let Condition::Player(pattern) = &self.condition {
+            return match is_player(state, pattern) {
+                true => Progress::Met,
+                false => Progress::NotYet,
+            };
+        }
+
+        if !self.is_watched(state) {
+            return Progress::NotYet;
+        }
+
+        let met = match &self.condition {
+            Condition::Status(status) => state.playback_status == *status,
+            Condition::TrackChange => {
+                let track = track_key(&state.metadata);
+                let first_track = self
+                    .first_tracks
+                    .entry(state.name.clone())
+                    .or_insert_with(|| track.clone());
+                *first_track != track
+            }
+            _ => return Progress::QueryPosition,
+        };
+
+        match met {
+            true => Progress::Met,
+            false => Progress::NotYet,
+        }
+    }
+
+    fn handle_vanished(&mut self, name: &str) {
+        self.first_tracks.remove(name);
+
+        // the next player to show up is watched instead
+        if self.fixed_player.is_none() && self.target.as_deref() == Some(name) {
+            self.target = None;
+        }
+    }
+}
+
+fn query_position(
+    condition: &Condition,
+    state: &PlayerState,
+) -> Result<PositionCheck, CommandError> {
+    let player = match common::player::get_player_by_bus_name(&state.name) {
+        Ok(Some(player)) => player,
+        Ok(None) => return Ok(PositionCheck::Gone),
+        Err(err) => {
+            return fail(
+                ErrorCode::DBus,
+                format!("Failed to get player {}: {err}", state.name),
+            )
+        }
+    };
+
+    let position = player.get_position().map_err(|err| {
+        CommandError::new(
+            ErrorCode::Unsupported,
+            format!("Failed to get the position: {err}"),
+        )
+    })?;
+
+    let rate = match state.playback_status {
+        PlaybackStatus::Playing => player.get_playback_rate().unwrap_or(1.0),
+        _ => 0.0,
+    };
+
+    Ok(check_position(condition, position, rate))
+}
+
+// never resolves without a deadline
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn wait_for(
+    mut waiter: Waiter,
+    timeout: Option<Duration>,
+) -> Result<PlayerState, CommandError> {
+    let (event_tx, mut event_rx) = mpsc::channel(16);
+    common::player::spawn_players_listener(event_tx).map_err(|err| {
+        CommandError::new(
+            ErrorCode::DBus,
+            format!("Failed to listen to the players: {err}"),
+        )
+    })?;
+
+    // a timeout too far away to be an instant is no timeout at all
+    let timeout_at = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    // when to query the position of the player again
+    let mut recheck: Option<(Instant, PlayerState)> = None;
+
+    loop {
+        let state = tokio::select! {
+            event = event_rx.recv() => match event {
+                Some(PlayersEvent::Changed(state)) => state,
+                Some(PlayersEvent::Vanished(name)) => {
+                    waiter.handle_vanished(&name);
+                    if recheck.as_ref().is_some_and(|(_, state)| state.name == name) {
+                        recheck = None;
+                    }
+                    continue;
+                }
+                None => return fail(ErrorCode::DBus, "Stopped listening to the players"),
+            },
+            _ = sleep_until(recheck.as_ref().map(|(at, _)| *at)) => {
+                match recheck.take() {
+                    Some((_, state)) => state,
+                    None => continue,
+                }
+            },
+            _ = sleep_until(timeout_at) => {
+                return fail(
+                    ErrorCode::Timeout,
+                    format!("Timed out waiting for {}", waiter.condition),
+                );
+            },
+        };
+
+        match waiter.handle_state(&state) {
+            Progress::Met => return Ok(state),
+            Progress::NotYet => {}
+            Progress::QueryPosition => match query_position(&waiter.condition, &state)? {
+                PositionCheck::Met => return Ok(state),
+                PositionCheck::In(duration) => {
+                    recheck = Some((Instant::now() + duration.min(POSITION_INTERVAL), state))
+                }
+                PositionCheck::AfterSeek => {
+                    recheck = Some((Instant::now() + POSITION_INTERVAL, state))
+                }
+                PositionCheck::Gone => recheck = None,
+            },
+        }
+    }
+}
+
+fn wait_output(condition: &Condition, state: &PlayerState) -> CommandOutput {
+    let player = format!("Player {} ({})", state.identity, state.name);
+    let text = match condition {
+        Condition::Status(status) => format!("{player} is {:?}", status),
+        Condition::TrackChange => format!(
+            "{player} changed the track to {}",
+            state.metadata.title().unwrap_or("an untitled track")
+        ),
+        Condition::Player(_) => format!("{player} is running"),
+        Condition::PositionOver(time) => format!("{player} is past {}", format_duration(*time)),
+        Condition::PositionUnder(time) => {
+            format!("{player} is before {}", format_duration(*time))
+        }
+    };
+
+    CommandOutput::new(
+        text,
+        json!({
+            "condition": condition.to_string(),
+            "player": player_state_json(state),
+        }),
+    )
+}
+
+/// Block until the condition holds for the player, listening to its changes
+pub fn wait_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
+    let condition = match ctx.args.get(2) {
+        None => {
+            return fail(
+                ErrorCode::InvalidArgument,
+                "Condition not provided, please use eg: status=playing, track-change, player=spotify or position>1:00",
+            )
+        }
+        Some(arg) => arg.parse::<Condition>().map_err(|e| {
+            CommandError::new(ErrorCode::InvalidArgument, format!("Invalid condition: {e}"))
+        })?,
+    };
+
+    let timeout = ctx
+        .args
+        .flags
+        .get("timeout")
+        .map(|timeout| {
+            parse_time(timeout, SECOND).map_err(|e| {
+                CommandError::new(ErrorCode::InvalidArgument, format!("Invalid timeout: {e}"))
+            })
+        })
+        .transpose()?;
+
+    if is_broadcast(&ctx) {
+        return fail(
+            ErrorCode::InvalidArgument,
+            "The wait command can't be used with --all-players or --players",
+        );
+    }
+
+    let aliases = common::config::load_config()
+        .map_err(|err| {
+            CommandError::new(ErrorCode::Failed, format!("Failed to load config: {err}"))
+        })?
+        .aliases;
+    let resolve = |name: &str| resolve_alias(name, &aliases).to_string();
+
+    let condition = match condition {
+        Condition::Player(pattern) => Condition::Player(resolve(&pattern)),
+        condition => condition,
+    };
+    let fixed_player = ctx.args.flags.get("player").map(|name| resolve(name));
+
+    // the preferred player when the wait started, a player switch doesn't
+    // change it
+    let target = match (&condition, &fixed_player) {
+        (Condition::Player(_), _) | (_, Some(_)) => None,
+        _ => common::player::get_preferred_player_or_first()
+            .map_err(|err| lookup_error("Failed to get player", err))?
+            .map(|player| player.bus_name().to_string()),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to start the runtime: {err}"),
+            )
+        })?;
+
+    let waiter = Waiter::new(condition.clone(), fixed_player, target);
+    let state = runtime.block_on(wait_for(waiter, timeout))?;
+
+    print_output(&ctx, wait_output(&condition, &state));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player_state(name: &str, status: PlaybackStatus, track: &str) -> PlayerState {
+        PlayerState {
+            identity: name.to_string(),
+            name: format!("org.mpris.MediaPlayer2.{name}"),
+            metadata: mpris::Metadata::new(track),
+            playback_status: status,
+            loop_status: mpris::LoopStatus::None,
+            shuffle: false,
+            volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(
+            "status=Playing".parse(),
+            Ok(Condition::Status(PlaybackStatus::Playing))
+        );
+        assert_eq!("track-change".parse(), Ok(Condition::TrackChange));
+        assert_eq!(
+            "player=spotify".parse(),
+            Ok(Condition::Player(String::from("spotify")))
+        );
+        assert_eq!(
+            "position>60s".parse(),
+            Ok(Condition::PositionOver(Duration::from_secs(60)))
+        );
+        assert_eq!(
+            "position<1:30".parse(),
+            Ok(Condition::PositionUnder(Duration::from_secs(90)))
+        );
+
+        assert!("status=loud".parse::<Condition>().is_err());
+        assert!("player=".parse::<Condition>().is_err());
+        assert!("volume>0.5".parse::<Condition>().is_err());
+    }
+
+    #[test]
+    fn test_check_position() {
+        let over = Condition::PositionOver(Duration::from_secs(60));
+        let under = Condition::PositionUnder(Duration::from_secs(60));
+
+        assert_eq!(
+            check_position(&over, Duration::from_secs(61), 1.0),
+            PositionCheck::Met
+        );
+        assert_eq!(
+            check_position(&over, Duration::from_secs(50), 2.0),
+            PositionCheck::In(Duration::from_secs(5) + POSITION_MARGIN)
+        );
+        assert_eq!(
+            check_position(&over, Duration::from_secs(50), 0.0),
+            PositionCheck::AfterSeek
+        );
+        assert_eq!(
+            check_position(&over, Duration::from_secs(50), f64::MIN_POSITIVE),
+            PositionCheck::AfterSeek
+        );
+        assert_eq!(
+            check_position(&under, Duration::from_secs(50), 1.0),
+            PositionCheck::Met
+        );
+        assert_eq!(
+            check_position(&under, Duration::from_secs(61), 1.0),
+            PositionCheck::AfterSeek
+        );
+    }
+
+    #[test]
+    fn test_waiter() {
+        let mut waiter = Waiter::new(
+            Condition::Status(PlaybackStatus::Playing),
+            None,
+            Some(String::from("org.mpris.MediaPlayer2.mpv")),
+        );
+        let spotify = player_state("spotify", PlaybackStatus::Playing, "/1");
+        assert_eq!(waiter.handle_state(&spotify), Progress::NotYet);
+        let mpv = player_state("mpv", PlaybackStatus::Playing, "/1");
+        assert_eq!(waiter.handle_state(&mpv), Progress::Met);
+
+        // the first track is the one to change from
+        let mut waiter = Waiter::new(Condition::TrackChange, Some(String::from("spot")), None);
+        assert_eq!(waiter.handle_state(&spotify), Progress::NotYet);
+        assert_eq!(waiter.handle_state(&mpv), Progress::NotYet);
+        assert_eq!(waiter.handle_state(&spotify), Progress::NotYet);
+        let next = player_state("spotify", PlaybackStatus::Playing, "/2");
+        assert_eq!(waiter.handle_state(&next), Progress::Met);
+
+        let mut waiter = Waiter::new(Condition::Player(String::from("mpv")), None, None);
+        assert_eq!(waiter.handle_state(&spotify), Progress::NotYet);
+        assert_eq!(waiter.handle_state(&mpv), Progress::Met);
+
+        // without a player, the first one to show up is watched
+        let mut waiter = Waiter::new(Condition::PositionOver(SECOND), None, None);
+        assert_eq!(waiter.handle_state(&mpv), Progress::QueryPosition);
+        assert_eq!(waiter.handle_state(&spotify), Progress::NotYet);
+        waiter.handle_vanished("org.mpris.MediaPlayer2.mpv");
+        assert_eq!(waiter.handle_state(&spotify), Progress::QueryPosition);
+    }
+}
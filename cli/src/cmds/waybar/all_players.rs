@@ -0,0 +1,145 @@
+use super::super::backoff::Backoff;
+use super::super::output::print_line;
+use super::super::printer::Printer;
+use super::formats::OutputFormat;
+use super::output::BarState;
+use common::config::WaybarConfig;
+use common::player::{PlayerState, PlayersEvent};
+use std::collections::BTreeMap;
+use tokio::sync::mpsc;
+
+/// Like `start_waybar_loop`, but showing every player in a single line.
+///
+/// Players that show up or go away are picked by the players listener, so
+/// the loop never has to start over.
+pub async fn start_all_players_loop(config: WaybarConfig, format: OutputFormat) {
+    if let Some(header) = format.header() {
+        print_line(header);
+    }
+
+    let mut printer = Printer::new();
+    let mut backoff = Backoff::new();
+
+    let (event_tx, mut event_rx) = mpsc::channel(16);
+    while let Err(err) = common::player::spawn_players_listener(event_tx.clone()) {
+        eprintln!("Failed to start players listener: {err:#}");
+        backoff.wait().await;
+    }
+    drop(event_tx);
+    backoff.reset();
+
+    let mut listener = common::player::PreferredPlayerListener::new();
+    let mut preferred_rx = None;
+
+    let mut preferred = common::player::get_preferred_player_name()
+        .ok()
+        .flatten()
+        .filter(|name| !name.is_empty());
+    let mut players = BTreeMap::new();
+
+    show_all(
+        &mut printer,
+        &players,
+        preferred.as_deref(),
+        &config,
+        format,
+    );
+
+    loop {
+        let rx = match preferred_rx {
+            Some(ref mut rx) => rx,
+            None => match listener.start() {
+                Ok(rx) => {
+                    backoff.reset();
+                    preferred_rx.insert(rx)
+                }
+                Err(err) => {
+                    eprintln!("Failed to watch the preferred player: {err:#}");
+                    // keep showing the players while waiting to retry
+                    tokio::select! {
+                        event = event_rx.recv() => {
+                            match event {
+                                Some(event) => handle_event(&mut players, event),
+                                None => break,
+                            }
+                            show_all(&mut printer, &players, preferred.as_deref(), &config, format);
+                        },
+                        _ = backoff.wait() => {},
+                    }
+                    continue;
+                }
+            },
+        };
+
+        tokio::select! {
+            event = event_rx.recv() => {
+                match event {
+                    Some(event) => handle_event(&mut players, event),
+                    None => break,
+                }
+            },
+            new_player_name = rx.recv() => {
+                match new_player_name {
+                    Some(Ok(name)) => {
+                        let name = name.trim().to_string();
+                        preferred = (!name.is_empty()).then_some(name);
+                    }
+                    Some(Err(err)) => eprintln!("Failed to read preferred player: {err:#}"),
+                    None => {
+                        eprintln!("Stopped watching the preferred player, restarting");
+                        preferred_rx = None;
+                    }
+                }
+            }
+        }
+
+        show_all(
+            &mut printer,
+            &players,
+            preferred.as_deref(),
+            &config,
+            format,
+        );
+    }
+}
+
+fn handle_event(players: &mut BTreeMap<String, PlayerState>, event: PlayersEvent) {
+    match event {
+        PlayersEvent::Changed(state) => {
+            players.insert(state.name.clone(), state);
+        }
+        PlayersEvent::Vanished(name) => {
+            players.remove(&name);
+        }
+    }
+}
+
+fn show_all(
+    printer: &mut Printer,
+    players: &BTreeMap<String, PlayerState>,
+    preferred: Option<&str>,
+    config: &WaybarConfig,
+    format: OutputFormat,
+) {
+    if players.is_empty() {
+        printer.print(format.format_no_player(config));
+        return;
+    }
+
+    // same as get_preferred_player_or_first
+    let preferred = preferred
+        .filter(|name| players.contains_key(*name))
+        .or_else(|| players.keys().next().map(String::as_str));
+
+    let bars = players
+        .values()
+        .map(|state| BarState {
+            state,
+            position: None,
+            config,
+            marquee: None,
+        })
+        .collect::<Vec<_>>();
+
+    printer.print(format.format_all(&bars, preferred, &config.separator));
+}
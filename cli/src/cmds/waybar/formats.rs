@@ -0,0 +1,282 @@
+use super::markup::escape_gtk_markup;
+use super::output::BarState;
+use common::config::WaybarConfig;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The status bars the live loop can talk to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Waybar,
+    Polybar,
+    I3blocks,
+    I3bar,
+    Eww,
+    Tmux,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 6] = [
+        OutputFormat::Waybar,
+        OutputFormat::Polybar,
+        OutputFormat::I3blocks,
+        OutputFormat::I3bar,
+        OutputFormat::Eww,
+        OutputFormat::Tmux,
+    ];
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            OutputFormat::Waybar => "waybar",
+            OutputFormat::Polybar => "polybar",
+            OutputFormat::I3blocks => "i3blocks",
+            OutputFormat::I3bar => "i3bar",
+            OutputFormat::Eww => "eww",
+            OutputFormat::Tmux => "tmux",
+        }
+    }
+
+    /// Printed once, before any state
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            // the i3bar protocol is an "infinite" array of block lists
+            OutputFormat::I3bar => Some("{\"version\":1}\n["),
+            _ => None,
+        }
+    }
+
+    pub fn format(&self, bar: &BarState) -> String {
+        match self {
+            OutputFormat::Waybar => json!({
+                "text": escape_gtk_markup(&bar.text()),
+                "tooltip": escape_gtk_markup(&bar.tooltip()),
+                "percentage": bar.percentage(),
+                "class": [bar.status_class(), bar.player_class()],
+                "alt": &bar.state.identity,
+            })
+            .to_string(),
+            OutputFormat::Polybar => format!(
+                "%{{A1:rstroller play-pause:}}%{{A3:rstroller next:}}\
+                 %{{A4:rstroller scroll-player up:}}%{{A5:rstroller scroll-player down:}}\
+                 {}%{{A}}%{{A}}%{{A}}%{{A}}",
+                escape_polybar(&bar.text())
+            ),
+            OutputFormat::I3blocks => json!({
+                "full_text": bar.text(),
+                "short_text": bar.short_text(),
+                "color": i3_color(bar.status_class()),
+            })
+            .to_string(),
+            OutputFormat::I3bar => format!(
+                "[{}],",
+                json!({
+                    "name": "rstroller",
+                    "instance": &bar.state.name,
+                    "full_text": bar.text(),
+                    "short_text": bar.short_text(),
+                    "color": i3_color(bar.status_class()),
+                })
+            ),
+            OutputFormat::Eww => format_eww(bar).to_string(),
+            OutputFormat::Tmux => escape_tmux(&bar.text()),
+        }
+    }
+
+    /// Every player in a single line, with the preferred one highlighted
+    pub fn format_all(
+        &self,
+        bars: &[BarState],
+        preferred: Option<&str>,
+        separator: &str,
+    ) -> String {
+        let is_preferred = |bar: &BarState| Some(bar.state.name.as_str()) == preferred;
+        let preferred_bar = bars.iter().find(|bar| is_preferred(bar));
+
+        let join_texts = |escape: &dyn Fn(&str) -> String, highlight: (&str, &str)| {
+            bars.iter()
+                .map(|bar| {
+                    let text = escape(&bar.text());
+                    if is_preferred(bar) {
+                        format!("{}{}{}", highlight.0, text, highlight.1)
+                    } else {
+                        text
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(&escape(separator))
+        };
+
+        let pango = ("<b>", "</b>");
+
+        match self {
+            OutputFormat::Waybar => json!({
+                "text": join_texts(&escape_gtk_markup, pango),
+                "tooltip": bars
+                    .iter()
+                    .map(|bar| escape_gtk_markup(&bar.summary()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                "percentage": preferred_bar.map(|bar| bar.percentage()).unwrap_or(0),
+                "class": match preferred_bar {
+                    Some(bar) => vec![bar.status_class().to_string(), bar.player_class()],
+                    None => vec![],
+                },
+                "alt": preferred_bar.map(|bar| bar.state.identity.as_str()),
+            })
+            .to_string(),
+            OutputFormat::Polybar => format!(
+                "%{{A1:rstroller play-pause:}}%{{A3:rstroller next:}}\
+                 %{{A4:rstroller scroll-player up:}}%{{A5:rstroller scroll-player down:}}\
+                 {}%{{A}}%{{A}}%{{A}}%{{A}}",
+                join_texts(&escape_polybar, ("%{+u}", "%{-u}"))
+            ),
+            OutputFormat::I3blocks => json!({
+                "full_text": join_texts(&|s: &str| s.to_string(), ("", "")),
+                "short_text": preferred_bar.map(|bar| bar.short_text()),
+                "color": i3_color(preferred_bar.map(|bar| bar.status_class()).unwrap_or("no-player")),
+            })
+            .to_string(),
+            OutputFormat::I3bar => format!(
+                "[{}],",
+                json!({
+                    "name": "rstroller",
+                    "markup": "pango",
+                    "full_text": join_texts(&escape_gtk_markup, pango),
+                    "short_text": preferred_bar.map(|bar| escape_gtk_markup(&bar.short_text())),
+                    "color": i3_color(preferred_bar.map(|bar| bar.status_class()).unwrap_or("no-player")),
+                })
+            ),
+            OutputFormat::Eww => json!({
+                "preferred": preferred,
+                "players": bars
+                    .iter()
+                    .map(|bar| {
+                        let mut state = format_eww(bar);
+                        state["preferred"] = json!(is_preferred(bar));
+                        state
+                    })
+                    .collect::<Vec<_>>(),
+                "text": join_texts(&|s: &str| s.to_string(), ("", "")),
+            })
+            .to_string(),
+            OutputFormat::Tmux => join_texts(&escape_tmux, ("#[bold]", "#[nobold]")),
+        }
+    }
+
+    pub fn format_no_player(&self, config: &WaybarConfig) -> String {
+        let text = &config.no_player_text;
+        let tooltip = &config.no_player_tooltip;
+
+        match self {
+            OutputFormat::Waybar => json!({
+                "text": escape_gtk_markup(text),
+                "tooltip": escape_gtk_markup(tooltip),
+                "class": "no-player",
+                "alt": "no-player",
+            })
+            .to_string(),
+            OutputFormat::Polybar => escape_polybar(text),
+            OutputFormat::I3blocks => json!({
+                "full_text": text,
+                "color": i3_color("no-player"),
+            })
+            .to_string(),
+            OutputFormat::I3bar => format!(
+                "[{}],",
+                json!({
+                    "name": "rstroller",
+                    "full_text": text,
+                    "color": i3_color("no-player"),
+                })
+            ),
+            OutputFormat::Eww => json!({
+                "status": "no-player",
+                "text": text,
+                "tooltip": tooltip,
+            })
+            .to_string(),
+            OutputFormat::Tmux => escape_tmux(text),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::ALL
+            .into_iter()
+            .find(|format| format.value() == s)
+            .ok_or_else(|| {
+                let valid = OutputFormat::ALL.map(|f| f.value()).join(", ");
+                format!("Invalid output format {s}, please use one of: {valid}")
+            })
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+// eww gets everything, so the widgets can be built in yuck
+fn format_eww(bar: &BarState) -> Value {
+    let metadata = &bar.state.metadata;
+
+    json!({
+        "status": bar.status_class(),
+        "player": &bar.state.name,
+        "identity": &bar.state.identity,
+        "title": bar.title(),
+        "artists": bar.artists(),
+        "album": metadata.album_name(),
+        "art_url": metadata.art_url(),
+        "position": bar.position.map(|p| p.as_secs()),
+        "length": bar.length().map(|l| l.as_secs()),
+        "percentage": bar.percentage(),
+        "volume": bar.state.volume,
+        "loop": format!("{:?}", bar.state.loop_status),
+        "shuffle": bar.state.shuffle,
+        "text": bar.text(),
+        "tooltip": bar.tooltip(),
+    })
+}
+
+fn i3_color(status: &str) -> &'static str {
+    match status {
+        "playing" => "#ffffff",
+        "paused" => "#aaaaaa",
+        _ => "#666666",
+    }
+}
+
+// % starts a formatting tag in polybar
+fn escape_polybar(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+// # starts a format in tmux
+fn escape_tmux(text: &str) -> String {
+    text.replace('#', "##")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_format_from_str() {
+        for format in OutputFormat::ALL {
+            assert_eq!(format.value().parse::<OutputFormat>(), Ok(format));
+        }
+        assert!("lemonbar".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_polybar("100% pure"), "100%% pure");
+        assert_eq!(escape_tmux("track #1"), "track ##1");
+    }
+}
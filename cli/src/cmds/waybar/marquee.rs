@@ -0,0 +1,181 @@
+use common::config::MarqueeConfig;
+use common::utils::{display_width, split_graphemes};
+
+/// Scroll a text that doesn't fit in `width` columns.
+///
+/// The text moves by one grapheme cluster at a time, so accents and emoji
+/// sequences are never split.
+///
+/// The rotation happens on the raw text, the output formats escape only the
+/// visible window, so an entity like `&amp;` is never split in half.
+pub struct Marquee {
+    width: usize,
+    separator: Vec<String>,
+    pause_steps: usize,
+    text: Vec<String>,
+    offset: usize,
+    paused_for: usize,
+}
+
+impl Marquee {
+    pub fn new(config: &MarqueeConfig) -> Self {
+        let pause_steps = (config.pause / config.speed().as_secs_f64()).round() as usize;
+
+        Marquee {
+            width: config.width.max(1),
+            separator: to_graphemes(&config.separator),
+            pause_steps,
+            text: vec![],
+            offset: 0,
+            paused_for: 0,
+        }
+    }
+
+    /// Set the text to scroll, starting over if it's a new one
+    pub fn set_text(&mut self, text: &str) {
+        let text = to_graphemes(text);
+        if text == self.text {
+            return;
+        }
+
+        self.text = text;
+        self.offset = 0;
+        self.paused_for = 0;
+    }
+
+    fn fits(&self) -> bool {
+        display_width(&self.text.concat()) <= self.width
+    }
+
+    /// Move the text by one grapheme, unless it's pausing at the start
+    pub fn step(&mut self) {
+        if self.fits() {
+            return;
+        }
+
+        if self.offset == 0 && self.paused_for < self.pause_steps {
+            self.paused_for += 1;
+            return;
+        }
+
+        let loop_len = self.text.len() + self.separator.len();
+        self.offset = (self.offset + 1) % loop_len;
+        if self.offset == 0 {
+            self.paused_for = 0;
+        }
+    }
+
+    /// The visible part of the text
+    pub fn render(&self) -> String {
+        if self.fits() {
+            return self.text.concat();
+        }
+
+        let mut width = 0;
+        let mut visible = String::new();
+
+        for grapheme in self
+            .text
+            .iter()
+            .chain(self.separator.iter())
+            .cycle()
+            .skip(self.offset)
+        {
+            width += display_width(grapheme);
+            if width > self.width {
+                break;
+            }
+            visible.push_str(grapheme);
+        }
+
+        visible
+    }
+}
+
+fn to_graphemes(s: &str) -> Vec<String> {
+    split_graphemes(s).into_iter().map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::markup::escape_gtk_markup;
+    use super::*;
+
+    fn new_marquee(width: usize, pause_steps: usize) -> Marquee {
+        Marquee::new(&MarqueeConfig {
+            enabled: true,
+            width,
+            speed: 1.0,
+            pause: pause_steps as f64,
+            separator: " | ".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_short_text_does_not_scroll() {
+        let mut marquee = new_marquee(10, 0);
+        marquee.set_text("short");
+        marquee.step();
+
+        assert_eq!(marquee.render(), "short");
+    }
+
+    #[test]
+    fn test_scroll_and_wrap() {
+        let mut marquee = new_marquee(4, 0);
+        marquee.set_text("abcdef");
+
+        assert_eq!(marquee.render(), "abcd");
+        marquee.step();
+        assert_eq!(marquee.render(), "bcde");
+
+        for _ in 0..5 {
+            marquee.step();
+        }
+        assert_eq!(marquee.render(), " | a");
+    }
+
+    #[test]
+    fn test_pause_at_start() {
+        let mut marquee = new_marquee(4, 2);
+        marquee.set_text("abcdef");
+
+        marquee.step();
+        marquee.step();
+        assert_eq!(marquee.render(), "abcd");
+        marquee.step();
+        assert_eq!(marquee.render(), "bcde");
+    }
+
+    #[test]
+    fn test_restart_on_new_text() {
+        let mut marquee = new_marquee(4, 0);
+        marquee.set_text("abcdef");
+        marquee.step();
+        marquee.set_text("ghijkl");
+
+        assert_eq!(marquee.render(), "ghij");
+    }
+
+    #[test]
+    fn test_wide_chars() {
+        let mut marquee = new_marquee(5, 0);
+        marquee.set_text("日本語です");
+
+        assert_eq!(marquee.render(), "日本");
+        marquee.step();
+        assert_eq!(marquee.render(), "本語");
+    }
+
+    #[test]
+    fn test_markup_safe() {
+        let mut marquee = new_marquee(3, 0);
+        marquee.set_text("a&b&c");
+
+        assert_eq!(escape_gtk_markup(&marquee.render()), "a&amp;b");
+        marquee.step();
+        assert_eq!(escape_gtk_markup(&marquee.render()), "&amp;b&amp;");
+        marquee.step();
+        assert_eq!(escape_gtk_markup(&marquee.render()), "b&amp;c");
+    }
+}
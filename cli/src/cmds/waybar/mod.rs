@@ -1,43 +1,102 @@
+mod all_players;
+mod formats;
 mod markup;
+mod marquee;
+mod output;
 
+use super::backoff::Backoff;
+use super::output::{fail, print_line, CommandError, ErrorCode};
+use super::printer::Printer;
 use super::CommandName;
-use crate::core_definition::CommandExecContext;
+use crate::core_definition::{CommandExecContext, CommandResult};
+use all_players::start_all_players_loop;
 use anyhow::Result as AnyResult;
+use common::config::WaybarConfig;
+use common::err::LogErr;
 use common::player::{MprisWrapper, PlayerState};
-use markup::escape_gtk_markup;
-use mpris::DBusError;
-use serde_json::json;
-use std::process;
+use formats::OutputFormat;
+use marquee::Marquee;
+use output::{format_marquee_text, BarState};
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
+use tokio::time::MissedTickBehavior;
 
-pub fn waybar_cmd(ctx: CommandExecContext<CommandName>) {
+pub fn waybar_cmd(ctx: CommandExecContext<CommandName>) -> CommandResult {
     if ctx.args.flags.contains_key("player") {
-        eprintln!("Waybar mode does not support the --player flag");
-        process::exit(1);
+        return fail(
+            ErrorCode::InvalidArgument,
+            "Waybar mode does not support the --player flag",
+        );
     };
 
-    tokio::runtime::Builder::new_current_thread()
+    let format = match ctx.args.flags.get("output") {
+        None => OutputFormat::Waybar,
+        Some(format) => format
+            .parse()
+            .map_err(|e| CommandError::new(ErrorCode::InvalidArgument, e))?,
+    };
+
+    let config = common::config::load_config().map_err(|err| {
+        CommandError::new(ErrorCode::Failed, format!("Failed to load config: {err:#}"))
+    })?;
+    common::player::spawn_policies(&config).map_err(|err| {
+        CommandError::new(
+            ErrorCode::Failed,
+            format!("Failed to start the policies: {err:#}"),
+        )
+    })?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
-        .unwrap()
-        .block_on(start_waybar_loop());
+        .map_err(|err| {
+            CommandError::new(
+                ErrorCode::Failed,
+                format!("Failed to start the runtime: {err}"),
+            )
+        })?;
+
+    if ctx.args.flags.contains_key("all") {
+        runtime.block_on(start_all_players_loop(config.waybar, format));
+    } else {
+        runtime.block_on(start_waybar_loop(config.waybar, format));
+    }
+    Ok(())
 }
 
-pub async fn start_waybar_loop() {
-    let mut listener = common::player::PreferredPlayerListener::new();
-    let mut player_rx = listener.start().expect("Failed to start listener");
+pub async fn start_waybar_loop(config: WaybarConfig, format: OutputFormat) {
+    if let Some(header) = format.header() {
+        print_line(header);
+    }
+
+    let mut printer = Printer::new();
+    let mut backoff = Backoff::new();
 
-    let mut had_prev_player = true;
+    let mut listener = common::player::PreferredPlayerListener::new();
+    let mut player_rx = None;
 
     loop {
-        let player = common::player::get_preferred_player_or_first();
-        if player.is_err() {
-            eprintln!("Failed to get preferred player, let's just try again?");
-            eprintln!("Error: {:?}", player.err());
-            continue;
-        }
-        let player = player.unwrap();
+        let rx = match player_rx {
+            Some(ref mut rx) => rx,
+            None => match listener.start() {
+                Ok(rx) => player_rx.insert(rx),
+                Err(err) => {
+                    eprintln!("Failed to watch the preferred player: {err:#}");
+                    backoff.wait().await;
+                    continue;
+                }
+            },
+        };
+
+        let player = match common::player::get_preferred_player_or_first() {
+            Ok(player) => player,
+            Err(err) => {
+                eprintln!("Failed to get preferred player: {err:#}");
+                backoff.wait().await;
+                continue;
+            }
+        };
 
         match player {
             Some(ref player) => {
@@ -47,123 +106,121 @@ pub async fn start_waybar_loop() {
                 let (event_tx, event_rx) = mpsc::channel(1);
 
                 common::player::set_preferred_player_name(player_name)
-                    .expect("Failed to set player");
+                    .log_err("Failed to set preferred player");
 
-                wrapper
-                    .start_listener(event_tx)
-                    .expect("Failed to start listener");
+                if let Err(err) = wrapper.start_listener(event_tx) {
+                    eprintln!("Failed to listen to {player_name}: {err:#}");
+                    backoff.wait().await;
+                    continue;
+                }
 
-                player_rx = handle_player(player_name, event_rx, player_rx).await;
+                let end = handle_player(player, &config, format, &mut printer, event_rx, rx).await;
+
+                match end {
+                    PlayerLoopEnd::PreferredChanged => backoff.reset(),
+                    PlayerLoopEnd::PlayerGone { had_state: true } => backoff.reset(),
+                    // the player went away before saying anything, don't hammer it
+                    PlayerLoopEnd::PlayerGone { had_state: false } => backoff.wait().await,
+                    PlayerLoopEnd::WatcherClosed => {
+                        eprintln!("Stopped watching the preferred player, restarting");
+                        player_rx = None;
+                    }
+                }
             }
             None => {
-                if had_prev_player {
-                    println!(
-                        "{}",
-                        json!({"text": "Silence", "tooltip": "Nothing playing"}),
-                    );
-                }
-                std::thread::sleep(std::time::Duration::from_secs(1));
+                backoff.reset();
+                printer.print(format.format_no_player(&config));
+                tokio::time::sleep(Duration::from_secs(1)).await;
             }
         }
-        had_prev_player = player.is_some();
     }
 }
 
+/// Why `handle_player` stopped
+enum PlayerLoopEnd {
+    PreferredChanged,
+    PlayerGone { had_state: bool },
+    WatcherClosed,
+}
+
 async fn handle_player(
-    player_name: &str,
+    player: &mpris::Player,
+    config: &WaybarConfig,
+    format: OutputFormat,
+    printer: &mut Printer,
     mut event_rx: Receiver<PlayerState>,
-    mut player_rx: Receiver<AnyResult<String>>,
-) -> Receiver<AnyResult<String>> {
+    player_rx: &mut Receiver<AnyResult<String>>,
+) -> PlayerLoopEnd {
+    let player_name = player.bus_name();
+    let mut last_state: Option<PlayerState> = None;
+
+    // the position is not sent by the listener, so we refresh it while playing
+    let mut ticker = tokio::time::interval(config.interval());
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut marquee = config
+        .marquee
+        .enabled
+        .then(|| Marquee::new(&config.marquee));
+    let mut marquee_ticker = tokio::time::interval(config.marquee.speed());
+    marquee_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
     loop {
         tokio::select! {
             state = event_rx.recv() => {
                 if let Some(state) = state {
-                    show(state).expect("Failed to show player state");
+                    if let Some(marquee) = marquee.as_mut() {
+                        marquee.set_text(&format_marquee_text(&state));
+                    }
+                    printer.print(format_state(player, &state, config, format, marquee.as_ref()));
+                    last_state = Some(state);
                 } else {
-                    break;
+                    return PlayerLoopEnd::PlayerGone { had_state: last_state.is_some() };
+                }
+            },
+            _ = ticker.tick() => {
+                if let Some(state) = &last_state {
+                    if state.playback_status == mpris::PlaybackStatus::Playing {
+                        printer.print(format_state(player, state, config, format, marquee.as_ref()));
+                    }
+                }
+            },
+            _ = marquee_ticker.tick(), if marquee.is_some() => {
+                if let (Some(state), Some(marquee)) = (&last_state, marquee.as_mut()) {
+                    if state.playback_status == mpris::PlaybackStatus::Playing {
+                        marquee.step();
+                        printer.print(format_state(player, state, config, format, Some(marquee)));
+                    }
                 }
             },
             new_player_name = player_rx.recv() => {
                 match new_player_name {
                     Some(Ok(new_player_name)) => {
-                        if new_player_name != player_name {
-                            break;
+                        if new_player_name.trim() != player_name {
+                            return PlayerLoopEnd::PreferredChanged;
                         }
                     },
-                    _ => break,
+                    Some(Err(err)) => eprintln!("Failed to read preferred player: {err:#}"),
+                    None => return PlayerLoopEnd::WatcherClosed,
                 }
             }
         }
     }
-    player_rx
 }
 
-fn show(state: PlayerState) -> Result<(), DBusError> {
-    let metadata = state.metadata;
-
-    let title = metadata.title().unwrap_or("Unknown title");
-    let artists = parse_artists(metadata.artists());
-    let album = metadata.album_name();
-
-    let icon = match state.playback_status {
-        mpris::PlaybackStatus::Playing => "",
-        mpris::PlaybackStatus::Paused => "",
-        mpris::PlaybackStatus::Stopped => "",
+fn format_state(
+    player: &mpris::Player,
+    state: &PlayerState,
+    config: &WaybarConfig,
+    format: OutputFormat,
+    marquee: Option<&Marquee>,
+) -> String {
+    let bar = BarState {
+        state,
+        position: player.get_position().ok(),
+        config,
+        marquee,
     };
 
-    let (line, tooltip) = match artists {
-        Some(artists) => (
-            format!(
-                "{} {} by {}",
-                icon,
-                escape_gtk_markup(&common::utils::truncate_string(title, 30)),
-                escape_gtk_markup(&common::utils::truncate_string(&artists, 20)),
-            ),
-            format!(
-                "{} by {}{}",
-                escape_gtk_markup(title),
-                escape_gtk_markup(&artists),
-                match album {
-                    Some(album) if !album.is_empty() =>
-                        format!(" from the album {}", escape_gtk_markup(album)),
-                    _ => "".to_string(),
-                }
-            ),
-        ),
-        None => (
-            format!(
-                "{} {}",
-                icon,
-                escape_gtk_markup(&common::utils::truncate_string(title, 40))
-            ),
-            escape_gtk_markup(title),
-        ),
-    };
-
-    let output = json!({
-        "text": &line,
-        "tooltip": &tooltip,
-    });
-
-    println!("{}", output);
-
-    Ok(())
-}
-
-fn parse_artists(artists: Option<Vec<&str>>) -> Option<String> {
-    match artists {
-        Some(artists) => {
-            if artists.is_empty() {
-                return None;
-            }
-            let joined_artists = artists.join(", ");
-
-            if joined_artists.is_empty() {
-                return None;
-            }
-
-            Some(joined_artists)
-        }
-        None => None,
-    }
+    format.format(&bar)
 }
@@ -0,0 +1,228 @@
+use super::marquee::Marquee;
+use common::config::{TimeDisplay, WaybarConfig};
+use common::player::PlayerState;
+use common::utils::{format_duration, truncate_string};
+use std::time::Duration;
+
+/// Everything the bars need to know about the player, the output formats
+/// take the plain text from here and escape it their own way
+pub struct BarState<'a> {
+    pub state: &'a PlayerState,
+    pub position: Option<Duration>,
+    pub config: &'a WaybarConfig,
+    pub marquee: Option<&'a Marquee>,
+}
+
+impl BarState<'_> {
+    pub fn title(&self) -> &str {
+        self.state.metadata.title().unwrap_or("Unknown title")
+    }
+
+    pub fn artists(&self) -> Option<String> {
+        parse_artists(self.state.metadata.artists())
+    }
+
+    pub fn length(&self) -> Option<Duration> {
+        self.state.metadata.length()
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self.state.playback_status {
+            mpris::PlaybackStatus::Playing => "",
+            mpris::PlaybackStatus::Paused => "",
+            mpris::PlaybackStatus::Stopped => "",
+        }
+    }
+
+    pub fn status_class(&self) -> &'static str {
+        match self.state.playback_status {
+            mpris::PlaybackStatus::Playing => "playing",
+            mpris::PlaybackStatus::Paused => "paused",
+            mpris::PlaybackStatus::Stopped => "stopped",
+        }
+    }
+
+    pub fn player_class(&self) -> String {
+        sanitize_class(common::player::player_part(&self.state.name))
+    }
+
+    /// The main line, with the icon, the track and the time
+    pub fn text(&self) -> String {
+        let track = match (self.marquee, self.artists()) {
+            (Some(marquee), _) => marquee.render(),
+            (None, Some(artists)) => format!(
+                "{} by {}",
+                truncate_string(self.title(), 30),
+                truncate_string(&artists, 20),
+            ),
+            (None, None) => truncate_string(self.title(), 40),
+        };
+
+        match self.time() {
+            Some(time) => format!("{} {} {}", self.icon(), track, time),
+            None => format!("{} {}", self.icon(), track),
+        }
+    }
+
+    /// A shorter line, for bars that fallback to it when there's no room
+    pub fn short_text(&self) -> String {
+        format!("{} {}", self.icon(), truncate_string(self.title(), 20))
+    }
+
+    pub fn tooltip(&self) -> String {
+        let album = self.state.metadata.album_name();
+
+        let mut tooltip = match self.artists() {
+            Some(artists) => format!(
+                "{} by {}{}",
+                self.title(),
+                artists,
+                match album {
+                    Some(album) if !album.is_empty() => format!(" from the album {}", album),
+                    _ => "".to_string(),
+                }
+            ),
+            None => self.title().to_string(),
+        };
+
+        if let Some(time) = self.time() {
+            tooltip.push_str(&format!("\n{}", time));
+        }
+
+        tooltip
+    }
+
+    /// A single line about the player and its track, for the player list
+    pub fn summary(&self) -> String {
+        let track = match self.artists() {
+            Some(artists) => format!("{} by {}", self.title(), artists),
+            None => self.title().to_string(),
+        };
+
+        format!(
+            "{} ({:?}): {}",
+            self.state.identity, self.state.playback_status, track
+        )
+    }
+
+    pub fn percentage(&self) -> u64 {
+        percentage(self.position, self.length())
+    }
+
+    pub fn time(&self) -> Option<String> {
+        format_time(self.config.time, self.position, self.length())
+    }
+}
+
+/// The raw text scrolled by the marquee, the untruncated title and artists
+pub fn format_marquee_text(state: &PlayerState) -> String {
+    let title = state.metadata.title().unwrap_or("Unknown title");
+
+    match parse_artists(state.metadata.artists()) {
+        Some(artists) => format!("{} by {}", title, artists),
+        None => title.to_string(),
+    }
+}
+
+// CSS classes can't have dots, spaces and so on
+fn sanitize_class(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn percentage(position: Option<Duration>, length: Option<Duration>) -> u64 {
+    match (position, length) {
+        (Some(position), Some(length)) if !length.is_zero() => {
+            let ratio = position.as_secs_f64() / length.as_secs_f64();
+            (ratio.clamp(0.0, 1.0) * 100.0).round() as u64
+        }
+        _ => 0,
+    }
+}
+
+fn format_time(
+    display: TimeDisplay,
+    position: Option<Duration>,
+    length: Option<Duration>,
+) -> Option<String> {
+    let position = position?;
+
+    match (display, length) {
+        (TimeDisplay::None, _) => None,
+        (TimeDisplay::Elapsed, Some(length)) => Some(format!(
+            "{}/{}",
+            format_duration(position),
+            format_duration(length)
+        )),
+        (TimeDisplay::Elapsed, None) => Some(format_duration(position)),
+        (TimeDisplay::Remaining, Some(length)) => Some(format!(
+            "-{}",
+            format_duration(length.saturating_sub(position))
+        )),
+        (TimeDisplay::Remaining, None) => None,
+    }
+}
+
+fn parse_artists(artists: Option<Vec<&str>>) -> Option<String> {
+    match artists {
+        Some(artists) => {
+            if artists.is_empty() {
+                return None;
+            }
+            let joined_artists = artists.join(", ");
+
+            if joined_artists.is_empty() {
+                return None;
+            }
+
+            Some(joined_artists)
+        }
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentage() {
+        let length = Some(Duration::from_secs(200));
+
+        assert_eq!(percentage(Some(Duration::from_secs(50)), length), 25);
+        assert_eq!(percentage(Some(Duration::from_secs(300)), length), 100);
+        assert_eq!(percentage(None, length), 0);
+        assert_eq!(percentage(Some(Duration::from_secs(50)), None), 0);
+    }
+
+    #[test]
+    fn test_sanitize_class() {
+        assert_eq!(sanitize_class("spotify"), "spotify");
+        assert_eq!(sanitize_class("Mozilla Firefox"), "mozilla-firefox");
+        assert_eq!(sanitize_class("vlc.instance42"), "vlc-instance42");
+    }
+
+    #[test]
+    fn test_format_time() {
+        let position = Some(Duration::from_secs(83));
+        let length = Some(Duration::from_secs(225));
+
+        assert_eq!(format_time(TimeDisplay::None, position, length), None);
+        assert_eq!(
+            format_time(TimeDisplay::Elapsed, position, length),
+            Some("1:23/3:45".to_string())
+        );
+        assert_eq!(
+            format_time(TimeDisplay::Remaining, position, length),
+            Some("-2:22".to_string())
+        );
+    }
+}
@@ -1,8 +1,36 @@
 use super::args;
+use super::suggest::suggest;
 use super::CommandNameConstraints;
-use super::{Command, CommandExecContext, CommandFlag};
+use super::{Command, CommandExecContext, CommandFlag, ParsedArgs};
 use std::collections::HashMap;
-use std::process;
+use std::fmt::Display;
+
+// commands added before any group
+const DEFAULT_GROUP: &str = "Commands";
+
+#[derive(Debug)]
+pub enum RunError {
+    NoCommand,
+    /// The name and the closest command, if any
+    UnknownCommand(String, Option<String>),
+    InvalidArgs(anyhow::Error),
+    /// The command ran and returned an error
+    Failed(anyhow::Error),
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::NoCommand => write!(f, "No command provided"),
+            RunError::UnknownCommand(name, Some(suggestion)) => {
+                write!(f, "Unknown command {name}, did you mean {suggestion}?")
+            }
+            RunError::UnknownCommand(name, None) => write!(f, "Unknown command {name}"),
+            RunError::InvalidArgs(err) => write!(f, "{err}"),
+            RunError::Failed(err) => write!(f, "{err:#}"),
+        }
+    }
+}
 
 pub struct App<'a, CommandName>
 where
@@ -11,7 +39,11 @@ where
     pub name: String,
     pub description: String,
     pub commands: HashMap<CommandName, Command<'a, CommandName>>,
+    /// Titles and commands of each group, in the order they were added
+    pub groups: Vec<(&'a str, Vec<CommandName>)>,
     pub flags: Vec<&'a CommandFlag>,
+    /// Exit status and what it means, in the order they were added
+    pub exit_codes: Vec<(i32, &'a str)>,
     pub arg_parser: args::ArgParser,
 }
 
@@ -24,7 +56,9 @@ where
             name,
             description,
             commands: HashMap::new(),
+            groups: vec![],
             flags: vec![],
+            exit_codes: vec![],
             arg_parser: args::ArgParser::new(),
         }
     }
@@ -35,59 +69,246 @@ where
         self
     }
 
+    /// Document what the exit status `code` means, in the help and the man page
+    pub fn add_exit_code(mut self, code: i32, description: &'a str) -> Self {
+        self.exit_codes.push((code, description));
+        self
+    }
+
+    /// The commands added after this one are shown under `title` in the help
+    pub fn add_group(mut self, title: &'a str) -> Self {
+        self.groups.push((title, vec![]));
+        self
+    }
+
     pub fn add_command(mut self, cmd: Command<'a, CommandName>) -> Self {
+        match self.groups.last_mut() {
+            Some((_, names)) => names.push(cmd.name.clone()),
+            None => self.groups.push((DEFAULT_GROUP, vec![cmd.name.clone()])),
+        }
+
+        for flag in &cmd.flags {
+            self.arg_parser
+                .add_command_flag(&cmd.name.to_string(), flag);
+        }
+
         self.commands.insert(cmd.name.clone(), cmd);
         self
     }
 
-    pub fn run_cmd(&mut self) -> Option<CommandName> {
-        let args = match self.arg_parser.parse() {
-            Ok(args) => args,
-            Err(e) => {
-                eprintln!("Error parsing arguments: {}", e);
-                process::exit(1);
-            }
-        };
+    /// Every group, with its commands, in the order they were added
+    pub fn grouped_commands(&self) -> Vec<(&'a str, Vec<&Command<'a, CommandName>>)> {
+        self.groups
+            .iter()
+            .map(|(title, names)| {
+                let commands = names
+                    .iter()
+                    .filter_map(|name| self.commands.get(name))
+                    .collect();
+                (*title, commands)
+            })
+            .collect()
+    }
+
+    pub fn ordered_commands(&self) -> Vec<&Command<'a, CommandName>> {
+        self.grouped_commands()
+            .into_iter()
+            .flat_map(|(_, commands)| commands)
+            .collect()
+    }
+
+    /// Run the command named by the first positional arg of the parsed args
+    pub fn run_args(&self, args: ParsedArgs) -> Result<CommandName, RunError> {
+        let arg = args.get(1).ok_or(RunError::NoCommand)?;
+
+        let cmd = arg
+            .parse::<CommandName>()
+            .ok()
+            .and_then(|cmd_name| self.commands.get(&cmd_name))
+            .ok_or_else(|| {
+                let names = self
+                    .ordered_commands()
+                    .iter()
+                    .map(|cmd| cmd.name.to_string())
+                    .collect::<Vec<_>>();
+                let suggestion = suggest(arg, names.iter().map(String::as_str));
+
+                RunError::UnknownCommand(arg.to_string(), suggestion.map(String::from))
+            })?;
+
+        (cmd.handler)(CommandExecContext { app: self, args }).map_err(RunError::Failed)?;
+        Ok(cmd.name.clone())
+    }
+
+    pub fn help_text(&self) -> String {
+        let mut lines = vec![
+            format!("{} - {}", self.name, self.description),
+            String::new(),
+            format!("Usage: {} [flags] <command> [args]", self.name),
+            String::new(),
+            String::from("Flags:"),
+        ];
+
+        lines.extend(self.flags.iter().map(|flag| format_flag(flag)));
 
-        let arg = args.get(1)?;
-        let cmd_name: CommandName = arg.parse().ok()?;
+        for (title, commands) in self.grouped_commands() {
+            lines.push(String::new());
+            lines.push(format!("{}:", title));
 
-        match self.commands.get(&cmd_name) {
-            None => Some(cmd_name),
-            Some(cmd) => {
-                (cmd.handler)(CommandExecContext { app: self, args });
-                Some(cmd_name)
+            for cmd in commands {
+                if cmd.usage.is_empty() {
+                    lines.push(format!("  {} - {}", cmd.name, cmd.description));
+                } else {
+                    lines.push(format!(
+                        "  {} {} - {}",
+                        cmd.name, cmd.usage, cmd.description
+                    ));
+                }
             }
         }
+
+        if !self.exit_codes.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Exit status:"));
+            for (code, description) in &self.exit_codes {
+                lines.push(format!("  {} - {}", code, description));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(format!(
+            "Run \"{} help <command>\" for the arguments and examples of a command.",
+            self.name
+        ));
+        lines.push(String::new());
+
+        lines.join("\n")
     }
 
-    pub fn help(&self) {
-        println!("{} - {}", self.name, self.description);
+    /// Usage, arguments and examples of a single command
+    pub fn command_help_text(&self, cmd: &Command<'a, CommandName>) -> String {
+        let mut lines = vec![
+            format!("{} {} {}", self.name, cmd.name, cmd.usage)
+                .trim_end()
+                .to_string(),
+            String::new(),
+            cmd.description.to_string(),
+        ];
 
-        println!();
-        println!("Flags:");
-        for flag in &self.flags {
-            let value = if flag.has_value { "=<value>" } else { "" };
-            let short_name = match flag.short_name {
-                None => String::from(""),
-                Some(v) => format!("-{}, ", v),
-            };
+        if !cmd.flags.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Flags:"));
+            lines.extend(cmd.flags.iter().map(|flag| format_flag(flag)));
+        }
 
-            println!(
-                "  {}--{}{} - {}",
-                short_name, flag.name, value, flag.description
-            );
+        if !cmd.args.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Arguments:"));
+            for (name, description) in &cmd.args {
+                lines.push(format!("  {} - {}", name, description));
+            }
         }
 
-        println!();
-        println!("Commands:");
-        // FIXME: order
-        for cmd in self.commands.values() {
-            if cmd.usage.is_empty() {
-                println!("  {} - {}", cmd.name, cmd.description);
-            } else {
-                println!("  {} {} - {}", cmd.name, cmd.usage, cmd.description);
+        if !cmd.examples.is_empty() {
+            lines.push(String::new());
+            lines.push(String::from("Examples:"));
+            for (command_line, description) in &cmd.examples {
+                lines.push(format!("  {}", command_line));
+                lines.push(format!("      {}", description));
             }
         }
+
+        lines.push(String::new());
+
+        lines.join("\n")
+    }
+}
+
+fn format_flag(flag: &CommandFlag) -> String {
+    let value = if flag.has_value { "=<value>" } else { "" };
+    let short_name = match flag.short_name {
+        None => String::from(""),
+        Some(v) => format!("-{}, ", v),
+    };
+
+    format!(
+        "  {}--{}{} - {}",
+        short_name, flag.name, value, flag.description
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_definition::CommandResult;
+
+    static OUTPUT_FLAG: CommandFlag = CommandFlag {
+        name: "output",
+        short_name: Some("o"),
+        description: "the output",
+        has_value: true,
+        completion: crate::core_definition::ArgCompletion::None,
+    };
+
+    fn noop(_: CommandExecContext<String>) -> CommandResult {
+        Ok(())
+    }
+
+    fn new_app() -> App<'static, String> {
+        App::new(String::from("app"), String::from("test app"))
+            .add_command(Command::new(String::from("help"), "show help", &noop))
+            .add_group("Playback")
+            .add_command(Command::new(String::from("play"), "play it", &noop))
+            .add_command(
+                Command::new_with_usage(String::from("volume"), "[value]", "the volume", &noop)
+                    .with_flag(&OUTPUT_FLAG)
+                    .with_arg("value", "between 0 and 1")
+                    .with_example("app volume 0.5", "half the volume"),
+            )
+            .add_command(Command::new(String::from("pause"), "pause it", &noop))
+            .add_exit_code(0, "it worked")
+            .add_exit_code(2, "invalid argument")
+    }
+
+    #[test]
+    fn test_grouped_commands_order() {
+        let app = new_app();
+
+        let groups = app
+            .grouped_commands()
+            .into_iter()
+            .map(|(title, commands)| {
+                let names = commands.iter().map(|cmd| cmd.name.as_str()).collect();
+                (title, names)
+            })
+            .collect::<Vec<(&str, Vec<&str>)>>();
+
+        assert_eq!(
+            groups,
+            vec![
+                ("Commands", vec!["help"]),
+                ("Playback", vec!["play", "volume", "pause"])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_help_text() {
+        let help = new_app().help_text();
+
+        assert!(help.contains("Playback:\n  play - play it\n  volume [value] - the volume\n"));
+        assert!(help.contains("Exit status:\n  0 - it worked\n  2 - invalid argument\n"));
+    }
+
+    #[test]
+    fn test_command_help_text() {
+        let app = new_app();
+        let cmd = app.commands.get("volume").unwrap();
+
+        assert_eq!(
+            app.command_help_text(cmd),
+            "app volume [value]\n\nthe volume\n\nFlags:\n  -o, --output=<value> - the output\n\nArguments:\n  value - between 0 and 1\n\n\
+             Examples:\n  app volume 0.5\n      half the volume\n"
+        );
     }
 }
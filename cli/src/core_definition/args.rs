@@ -1,6 +1,7 @@
+use super::suggest::suggest;
 use super::CommandFlag;
 use anyhow::Result as AnyResult;
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail};
 use std::collections::HashMap;
 use std::env;
 
@@ -18,117 +19,342 @@ impl ParsedArgs {
 
 pub struct ArgParser {
     flags: HashMap<String, &'static CommandFlag>,
+    // command name -> flags only available for that command
+    command_flags: HashMap<String, HashMap<String, &'static CommandFlag>>,
+}
+
+fn insert_flag(flags: &mut HashMap<String, &'static CommandFlag>, flag: &'static CommandFlag) {
+    flags.insert(format!("--{}", flag.name), flag);
+    if let Some(short_name) = flag.short_name {
+        flags.insert(format!("-{}", short_name), flag);
+    }
+}
+
+// "-5000" and "-0.5" are values, not flags
+fn is_flag(arg: &str) -> bool {
+    let mut chars = arg.chars();
+
+    chars.next() == Some('-')
+        && chars
+            .next()
+            .is_some_and(|c| !c.is_ascii_digit() && c != '.')
 }
 
 impl ArgParser {
     pub fn new() -> Self {
         ArgParser {
             flags: HashMap::new(),
+            command_flags: HashMap::new(),
         }
     }
 
     pub fn add_flag(&mut self, flag: &'static CommandFlag) {
-        self.flags.insert(format!("--{}", flag.name), flag);
-        if let Some(short_name) = flag.short_name {
-            self.flags.insert(format!("-{}", short_name), flag);
-        }
+        insert_flag(&mut self.flags, flag);
+    }
+
+    pub fn add_command_flag(&mut self, command_name: &str, flag: &'static CommandFlag) {
+        insert_flag(
+            self.command_flags
+                .entry(command_name.to_string())
+                .or_default(),
+            flag,
+        );
     }
 
     pub fn parse(&self) -> AnyResult<ParsedArgs> {
         self.parse_from_iter(env::args())
     }
 
+    /// Split the flags from the positional args.
+    ///
+    /// The first positional arg after the program name is the command, its
+    /// own flags are only accepted after it. Everything after `--` is a
+    /// positional arg.
     pub fn parse_from_iter<I: IntoIterator<Item = String>>(
         &self,
         iter: I,
     ) -> AnyResult<ParsedArgs> {
         let mut args = vec![];
         let mut flags = HashMap::new();
+        let mut only_positionals = false;
+
+        // collected to look ahead for the command when a flag is misplaced
+        let mut iter = iter.into_iter().collect::<Vec<_>>().into_iter();
+        // the program name
+        args.extend(iter.next());
 
-        for arg in iter {
-            match self.parse_flag(&arg).context("failed to parse flag")? {
-                Some((name, value)) => {
-                    flags.insert(name, value);
-                }
-                None => args.push(arg),
+        while let Some(arg) = iter.next() {
+            if only_positionals || !is_flag(&arg) {
+                args.push(arg);
+                continue;
             }
+
+            if arg == "--" {
+                only_positionals = true;
+                continue;
+            }
+
+            let (flag_prefix, inline_value) = match arg.split_once('=') {
+                Some((prefix, value)) => (prefix, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+
+            let command_name = args.get(1).map(String::as_str);
+            let flag = self.find_flag(flag_prefix, command_name, iter.as_slice())?;
+
+            let value = match (flag.has_value, inline_value) {
+                (true, Some(value)) => value,
+                (true, None) => iter
+                    .next()
+                    .ok_or_else(|| anyhow!("Flag --{} requires a value", flag.name))?,
+                (false, None) => String::new(),
+                (false, Some(_)) => bail!("Flag --{} does not take a value", flag.name),
+            };
+
+            flags.insert(flag.name.to_string(), value);
         }
 
         Ok(ParsedArgs { args, flags })
     }
 
-    fn parse_flag(&self, arg: &str) -> AnyResult<Option<(String, String)>> {
-        if !arg.starts_with('-') {
-            return Ok(None);
+    /// The command among the args that are not parsed yet, skipping the
+    /// global flags and their values
+    fn next_command<'a>(&self, upcoming: &'a [String]) -> Option<&'a str> {
+        let mut upcoming = upcoming.iter();
+
+        while let Some(arg) = upcoming.next() {
+            if arg == "--" {
+                return upcoming.next().map(String::as_str);
+            }
+            if !is_flag(arg) {
+                return Some(arg);
+            }
+            if self
+                .flags
+                .get(arg.as_str())
+                .is_some_and(|flag| flag.has_value)
+            {
+                upcoming.next();
+            }
+        }
+
+        None
+    }
+
+    fn find_flag(
+        &self,
+        flag_prefix: &str,
+        command_name: Option<&str>,
+        upcoming: &[String],
+    ) -> AnyResult<&'static CommandFlag> {
+        let command_flags = command_name.and_then(|name| self.command_flags.get(name));
+
+        if let Some(flag) = self
+            .flags
+            .get(flag_prefix)
+            .or_else(|| command_flags.and_then(|flags| flags.get(flag_prefix)))
+        {
+            return Ok(flag);
         }
 
-        let splitted_arg = arg.split('=').collect::<Vec<&str>>();
-        let flag_prefix = splitted_arg
-            .first()
-            .map(|s| s.to_string())
-            .context("invalid flag")?;
-
-        let flag_value = splitted_arg.get(1).map(|s| s.to_string());
-
-        let flag = match self.flags.get(&flag_prefix) {
-            None => return Err(anyhow!("Unknown flag: {}", flag_prefix)),
-            Some(flag) => flag,
-        };
-
-        if flag.has_value != flag_value.is_some() {
-            let expected = flag.has_value;
-            Err(anyhow!(
-                "Flag {} {} value",
-                flag.name,
-                if expected {
-                    "requires"
-                } else {
-                    "does not require"
-                },
-            ))?;
+        // a flag of another command, or of a command that is not there yet
+        let mut other_commands: Vec<&str> = self
+            .command_flags
+            .iter()
+            .filter(|(_, flags)| flags.contains_key(flag_prefix))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        other_commands.sort_unstable();
+
+        if command_name.is_none() {
+            if let Some(next_command) = self
+                .next_command(upcoming)
+                .filter(|name| other_commands.contains(name))
+            {
+                bail!(
+                    "Flag {} goes after the command name: {} {}",
+                    flag_prefix,
+                    next_command,
+                    flag_prefix
+                );
+            }
+        }
+
+        match other_commands.as_slice() {
+            [] => {}
+            [other_command] => bail!(
+                "Flag {} is only available for the {} command, after its name",
+                flag_prefix,
+                other_command
+            ),
+            _ => bail!(
+                "Flag {} is only available for the {} commands, after their name",
+                flag_prefix,
+                other_commands.join(", ")
+            ),
         }
 
-        Ok(Some((
-            flag.name.to_string(),
-            flag_value.unwrap_or("".to_string()),
-        )))
+        let known_flags = self
+            .flags
+            .keys()
+            .chain(command_flags.into_iter().flat_map(|flags| flags.keys()))
+            .map(String::as_str);
+
+        match suggest(flag_prefix, known_flags) {
+            Some(suggestion) => bail!("Unknown flag {}, did you mean {}?", flag_prefix, suggestion),
+            None => bail!("Unknown flag {}", flag_prefix),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core_definition::CommandFlag;
+    use crate::core_definition::{ArgCompletion, CommandFlag};
 
-    #[test]
-    fn test_parse_flag() {
-        let args = vec![
-            "/usr/bin/rstroller".to_string(),
-            "--player=player1".to_string(),
-            "-s".to_string(),
-            "test".to_string(),
-        ];
+    static PLAYER_FLAG: CommandFlag = CommandFlag {
+        name: "player",
+        description: "sample flag with value",
+        short_name: Some("p"),
+        has_value: true,
+        completion: ArgCompletion::None,
+    };
+
+    static SAMPLE_FLAG: CommandFlag = CommandFlag {
+        name: "sample",
+        description: "sample flag without value",
+        short_name: Some("s"),
+        has_value: false,
+        completion: ArgCompletion::None,
+    };
 
+    static OUTPUT_FLAG: CommandFlag = CommandFlag {
+        name: "output",
+        description: "command flag",
+        short_name: Some("o"),
+        has_value: true,
+        completion: ArgCompletion::None,
+    };
+
+    static FOLLOW_FLAG: CommandFlag = CommandFlag {
+        name: "follow",
+        description: "flag of several commands",
+        short_name: None,
+        has_value: false,
+        completion: ArgCompletion::None,
+    };
+
+    fn new_parser() -> ArgParser {
         let mut parser = ArgParser::new();
+        parser.add_flag(&PLAYER_FLAG);
+        parser.add_flag(&SAMPLE_FLAG);
+        parser.add_command_flag("waybar", &OUTPUT_FLAG);
+        for command_name in ["volume", "status", "metadata"] {
+            parser.add_command_flag(command_name, &FOLLOW_FLAG);
+        }
+        parser
+    }
+
+    fn parse(args: &[&str]) -> AnyResult<ParsedArgs> {
+        new_parser().parse_from_iter(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        let parsed = parse(&["/usr/bin/rstroller", "--player=player1", "-s", "test"]).unwrap();
 
-        parser.add_flag(&CommandFlag {
-            name: "player",
-            description: "sample flag with value",
-            short_name: Some("p"),
-            has_value: true,
-        });
-        parser.add_flag(&CommandFlag {
-            name: "sample",
-            description: "sample flag without value",
-            short_name: Some("s"),
-            has_value: false,
-        });
-
-        let parsed = parser.parse_from_iter(args).unwrap();
         assert_eq!(
             parsed.args,
             vec!["/usr/bin/rstroller".to_string(), "test".to_string()]
         );
         assert_eq!(parsed.flags.get("player"), Some(&"player1".to_string()));
+        assert_eq!(parsed.flags.get("sample"), Some(&"".to_string()));
+    }
+
+    #[test]
+    fn test_space_separated_value() {
+        let parsed = parse(&["rstroller", "-p", "spotify", "status"]).unwrap();
+
+        assert_eq!(parsed.args, vec!["rstroller", "status"]);
+        assert_eq!(parsed.flags.get("player"), Some(&"spotify".to_string()));
+
+        assert!(parse(&["rstroller", "status", "--player"]).is_err());
+    }
+
+    #[test]
+    fn test_value_with_equals() {
+        let parsed = parse(&["rstroller", "--player=a=b", "status"]).unwrap();
+        assert_eq!(parsed.flags.get("player"), Some(&"a=b".to_string()));
+    }
+
+    #[test]
+    fn test_negative_numbers_are_positionals() {
+        let parsed = parse(&["rstroller", "position", "-5000", "-0.5"]).unwrap();
+        assert_eq!(parsed.args, vec!["rstroller", "position", "-5000", "-0.5"]);
+    }
+
+    #[test]
+    fn test_terminator() {
+        let parsed = parse(&["rstroller", "metadata", "--", "--player"]).unwrap();
+
+        assert_eq!(parsed.args, vec!["rstroller", "metadata", "--player"]);
+        assert!(parsed.flags.is_empty());
+    }
+
+    #[test]
+    fn test_command_flags() {
+        let parsed = parse(&["rstroller", "waybar", "--output", "tmux"]).unwrap();
+        assert_eq!(parsed.flags.get("output"), Some(&"tmux".to_string()));
+
+        let err = parse(&["rstroller", "status", "--output=tmux"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Flag --output is only available for the waybar command, after its name"
+        );
+    }
+
+    #[test]
+    fn test_shared_command_flags() {
+        let parsed = parse(&["rstroller", "status", "--follow"]).unwrap();
+        assert_eq!(parsed.flags.get("follow"), Some(&"".to_string()));
+
+        let err = parse(&["rstroller", "--follow", "status"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Flag --follow goes after the command name: status --follow"
+        );
+
+        let err = parse(&["rstroller", "--follow", "-p", "mpv", "status"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Flag --follow goes after the command name: status --follow"
+        );
+
+        let err = parse(&["rstroller", "--follow", "play"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Flag --follow is only available for the metadata, status, volume commands, after their name"
+        );
+
+        let err = parse(&["rstroller", "play", "--follow"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Flag --follow is only available for the metadata, status, volume commands, after their name"
+        );
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let err = parse(&["rstroller", "--plyer=spotify", "status"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown flag --plyer, did you mean --player?"
+        );
+
+        let err = parse(&["rstroller", "--nope"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown flag --nope");
+
+        let err = parse(&["rstroller", "--sample=1"]).unwrap_err();
+        assert_eq!(err.to_string(), "Flag --sample does not take a value");
     }
 }
@@ -6,6 +6,9 @@ use std::str::FromStr;
 pub trait CommandNameConstraints: Eq + PartialEq + Hash + Clone + Display + FromStr {}
 impl<T> CommandNameConstraints for T where T: Eq + PartialEq + Hash + Clone + Display + FromStr {}
 
+/// What a command handler returns, the error is printed by whoever ran it
+pub type CommandResult = anyhow::Result<()>;
+
 pub struct CommandExecContext<'a, CommandName>
 where
     CommandName: CommandNameConstraints,
@@ -14,12 +17,23 @@ where
     pub args: ParsedArgs,
 }
 
+/// What the shell completion offers for a command argument or a flag value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgCompletion {
+    None,
+    /// A fixed list of values
+    Values(&'static [&'static str]),
+    /// The lines printed by a shell command, for values only known at runtime
+    Command(&'static str),
+}
+
 #[derive(Debug)]
 pub struct CommandFlag {
     pub name: &'static str,
     pub short_name: Option<&'static str>,
     pub description: &'static str,
     pub has_value: bool,
+    pub completion: ArgCompletion,
 }
 
 impl CommandFlag {
@@ -34,8 +48,14 @@ impl CommandFlag {
             short_name,
             description,
             has_value,
+            completion: ArgCompletion::None,
         }
     }
+
+    pub fn with_completion(mut self, completion: ArgCompletion) -> Self {
+        self.completion = completion;
+        self
+    }
 }
 
 pub struct Command<'a, CommandName>
@@ -45,7 +65,14 @@ where
     pub name: CommandName,
     pub description: &'a str,
     pub usage: &'a str,
-    pub handler: &'a dyn Fn(CommandExecContext<CommandName>),
+    pub completion: ArgCompletion,
+    /// Flags only available for this command
+    pub flags: Vec<&'static CommandFlag>,
+    /// Name and description of each argument
+    pub args: Vec<(&'a str, &'a str)>,
+    /// Command line and what it does
+    pub examples: Vec<(&'a str, &'a str)>,
+    pub handler: &'a dyn Fn(CommandExecContext<CommandName>) -> CommandResult,
 }
 
 impl<'a, CommandName> Command<'a, CommandName>
@@ -55,12 +82,16 @@ where
     pub fn new(
         name: CommandName,
         description: &'a str,
-        handler: &'a dyn Fn(CommandExecContext<CommandName>),
+        handler: &'a dyn Fn(CommandExecContext<CommandName>) -> CommandResult,
     ) -> Command<'a, CommandName> {
         Command {
             name,
             usage: "",
             description,
+            completion: ArgCompletion::None,
+            flags: vec![],
+            args: vec![],
+            examples: vec![],
             handler,
         }
     }
@@ -69,13 +100,38 @@ where
         name: CommandName,
         usage: &'a str,
         description: &'a str,
-        handler: &'a dyn Fn(CommandExecContext<CommandName>),
+        handler: &'a dyn Fn(CommandExecContext<CommandName>) -> CommandResult,
     ) -> Command<'a, CommandName> {
         Command {
             name,
             usage,
             description,
+            completion: ArgCompletion::None,
+            flags: vec![],
+            args: vec![],
+            examples: vec![],
             handler,
         }
     }
+
+    /// Complete the first argument of the command
+    pub fn with_completion(mut self, completion: ArgCompletion) -> Self {
+        self.completion = completion;
+        self
+    }
+
+    pub fn with_flag(mut self, flag: &'static CommandFlag) -> Self {
+        self.flags.push(flag);
+        self
+    }
+
+    pub fn with_arg(mut self, name: &'a str, description: &'a str) -> Self {
+        self.args.push((name, description));
+        self
+    }
+
+    pub fn with_example(mut self, command_line: &'a str, description: &'a str) -> Self {
+        self.examples.push((command_line, description));
+        self
+    }
 }
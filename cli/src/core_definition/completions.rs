@@ -0,0 +1,472 @@
+use super::CommandNameConstraints;
+use super::{App, ArgCompletion, Command, CommandFlag};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("Invalid shell {s}, please use bash, zsh or fish")),
+        }
+    }
+}
+
+impl<'a, CommandName> App<'a, CommandName>
+where
+    CommandName: CommandNameConstraints,
+{
+    /// A completion script for `shell`, with every command and flag
+    pub fn completions(&self, shell: Shell) -> String {
+        let commands = self.ordered_commands();
+
+        match shell {
+            Shell::Bash => bash_completions(&self.name, &commands, &self.flags),
+            Shell::Zsh => zsh_completions(&self.name, &commands, &self.flags),
+            Shell::Fish => fish_completions(&self.name, &commands, &self.flags),
+        }
+    }
+}
+
+// "--name" and "-n"
+fn flag_names(flag: &CommandFlag) -> Vec<String> {
+    let mut names = vec![format!("--{}", flag.name)];
+    if let Some(short_name) = flag.short_name {
+        names.push(format!("-{}", short_name));
+    }
+    names
+}
+
+fn bash_words(completion: ArgCompletion) -> Option<String> {
+    match completion {
+        ArgCompletion::None => None,
+        ArgCompletion::Values(values) => Some(values.join(" ")),
+        ArgCompletion::Command(command) => Some(format!("$({} 2>/dev/null)", command)),
+    }
+}
+
+fn bash_flag_words(flags: &[&CommandFlag]) -> String {
+    flags
+        .iter()
+        .flat_map(|flag| {
+            let suffix = if flag.has_value { "=" } else { "" };
+            flag_names(flag)
+                .into_iter()
+                .map(move |name| format!("{}{}", name, suffix))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash_completions<CommandName: CommandNameConstraints>(
+    name: &str,
+    commands: &[&Command<CommandName>],
+    flags: &[&CommandFlag],
+) -> String {
+    let fn_name = format!("_{}", name.replace('-', "_"));
+
+    // the same flag can be used by many commands
+    let mut value_flags: Vec<&CommandFlag> = vec![];
+    for flag in flags
+        .iter()
+        .chain(commands.iter().flat_map(|cmd| cmd.flags.iter()))
+    {
+        if flag.has_value && !value_flags.iter().any(|f| f.name == flag.name) {
+            value_flags.push(flag);
+        }
+    }
+
+    let value_flag_names = value_flags
+        .iter()
+        .flat_map(|flag| flag_names(flag))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // flags without completion still take the next word as value
+    let flag_values = value_flags
+        .iter()
+        .map(|flag| {
+            let reply = match bash_words(flag.completion) {
+                Some(words) => format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); ", words),
+                None => String::new(),
+            };
+            format!(
+                "        {}) {}return ;;\n",
+                flag_names(flag).join("|"),
+                reply
+            )
+        })
+        .collect::<String>();
+
+    let command_flags = commands
+        .iter()
+        .filter(|cmd| !cmd.flags.is_empty())
+        .map(|cmd| {
+            format!(
+                "            {}) flags=\"$flags {}\" ;;\n",
+                cmd.name,
+                bash_flag_words(&cmd.flags)
+            )
+        })
+        .collect::<String>();
+
+    let command_words = commands
+        .iter()
+        .map(|cmd| cmd.name.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let command_values = commands
+        .iter()
+        .filter_map(|cmd| {
+            let words = bash_words(cmd.completion)?;
+            Some(format!(
+                "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+                cmd.name, words
+            ))
+        })
+        .collect::<String>();
+
+    let global_flags = bash_flag_words(flags);
+
+    format!(
+        r#"{fn_name}() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    local value_flags=" {value_flag_names} "
+
+    # "--flag=value" is split in "--flag", "=" and "value"
+    local flag=""
+    if [[ "$cur" == "=" ]]; then
+        flag="$prev"
+        cur=""
+    elif [[ "$prev" == "=" ]]; then
+        flag="${{COMP_WORDS[COMP_CWORD-2]}}"
+    elif [[ "$value_flags" == *" $prev "* ]]; then
+        flag="$prev"
+    fi
+
+    case "$flag" in
+{flag_values}    esac
+
+    # the first argument that is not a flag or a flag value is the command
+    local cmd="" cmd_index=0 i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        local word="${{COMP_WORDS[i]}}" before="${{COMP_WORDS[i-1]}}"
+        if [[ "$word" == -* || "$word" == "=" || "$before" == "=" ]]; then
+            continue
+        fi
+        if [[ "$value_flags" == *" $before "* ]]; then
+            continue
+        fi
+        cmd="$word"
+        cmd_index=$i
+        break
+    done
+
+    if [[ "$cur" == -* ]]; then
+        local flags="{global_flags}"
+        case "$cmd" in
+{command_flags}        esac
+        COMPREPLY=($(compgen -W "$flags" -- "$cur"))
+        [[ "${{COMPREPLY[0]}}" == *= ]] && compopt -o nospace
+        return
+    fi
+
+    if [[ -z "$cmd" ]]; then
+        COMPREPLY=($(compgen -W "{command_words}" -- "$cur"))
+        return
+    fi
+
+    # only the first argument of the command is completed
+    [[ $((COMP_CWORD - cmd_index)) -eq 1 ]] || return
+
+    case "$cmd" in
+{command_values}    esac
+}}
+
+complete -F {fn_name} {name}
+"#
+    )
+}
+
+fn escape_zsh_quotes(s: &str) -> String {
+    s.replace('\'', "'\\''")
+}
+
+// brackets end the description of a flag
+fn escape_zsh_flag(s: &str) -> String {
+    escape_zsh_quotes(s).replace('[', "\\[").replace(']', "\\]")
+}
+
+// colons split the name and the description of a command
+fn escape_zsh_command(s: &str) -> String {
+    escape_zsh_quotes(s).replace(':', "\\:")
+}
+
+fn zsh_action(completion: ArgCompletion) -> String {
+    match completion {
+        ArgCompletion::None => String::new(),
+        ArgCompletion::Values(values) => format!("({})", values.join(" ")),
+        ArgCompletion::Command(command) => {
+            format!("{{compadd -- ${{(f)\"$({} 2>/dev/null)\"}}}}", command)
+        }
+    }
+}
+
+// one _arguments spec per line, "--flag=" accepts both "--flag=value" and
+// "--flag value"
+fn zsh_flag_specs(flags: &[&CommandFlag], indent: &str) -> String {
+    flags
+        .iter()
+        .flat_map(|flag| {
+            flag_names(flag).into_iter().map(move |flag_name| {
+                let description = escape_zsh_flag(flag.description);
+                if flag.has_value {
+                    format!(
+                        "{}'{}=[{}]:{}:{}' \\\n",
+                        indent,
+                        flag_name,
+                        description,
+                        flag.name,
+                        escape_zsh_quotes(&zsh_action(flag.completion))
+                    )
+                } else {
+                    format!("{}'{}[{}]' \\\n", indent, flag_name, description)
+                }
+            })
+        })
+        .collect()
+}
+
+fn zsh_completions<CommandName: CommandNameConstraints>(
+    name: &str,
+    commands: &[&Command<CommandName>],
+    flags: &[&CommandFlag],
+) -> String {
+    let fn_name = format!("_{}", name.replace('-', "_"));
+
+    let flag_specs = zsh_flag_specs(flags, "        ");
+
+    let command_specs = commands
+        .iter()
+        .map(|cmd| {
+            format!(
+                "        '{}:{}'\n",
+                cmd.name,
+                escape_zsh_command(cmd.description)
+            )
+        })
+        .collect::<String>();
+
+    let command_args = commands
+        .iter()
+        .filter(|cmd| !cmd.flags.is_empty() || cmd.completion != ArgCompletion::None)
+        .map(|cmd| {
+            format!(
+                "                {})\n                    _arguments \\\n{}                        '1: :{}' \\\n                        '*: :'\n                    ;;\n",
+                cmd.name,
+                zsh_flag_specs(&cmd.flags, "                        "),
+                escape_zsh_quotes(&zsh_action(cmd.completion))
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"#compdef {name}
+
+{fn_name}() {{
+    local -a commands
+    commands=(
+{command_specs}    )
+
+    local state
+    _arguments -C \
+{flag_specs}        '1: :->command' \
+        '*:: :->args'
+
+    case $state in
+        command)
+            _describe 'command' commands
+            ;;
+        args)
+            case $words[1] in
+{command_args}            esac
+            ;;
+    esac
+}}
+
+compdef {fn_name} {name}
+"#
+    )
+}
+
+fn escape_fish(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish_arguments(completion: ArgCompletion) -> Option<String> {
+    match completion {
+        ArgCompletion::None => None,
+        ArgCompletion::Values(values) => Some(values.join(" ")),
+        ArgCompletion::Command(command) => Some(format!("({} 2>/dev/null)", command)),
+    }
+}
+
+fn fish_flag(name: &str, flag: &CommandFlag, condition: Option<&str>) -> String {
+    let mut line = format!("complete -c {}", name);
+    if let Some(condition) = condition {
+        line.push_str(&format!(" -n '{}'", condition));
+    }
+    line.push_str(&format!(" -l {}", flag.name));
+    if let Some(short_name) = flag.short_name {
+        line.push_str(&format!(" -s {}", short_name));
+    }
+    if flag.has_value {
+        line.push_str(" -x");
+    }
+    if let Some(arguments) = fish_arguments(flag.completion) {
+        line.push_str(&format!(" -a '{}'", escape_fish(&arguments)));
+    }
+    line.push_str(&format!(" -d '{}'", escape_fish(flag.description)));
+    line
+}
+
+fn fish_completions<CommandName: CommandNameConstraints>(
+    name: &str,
+    commands: &[&Command<CommandName>],
+    flags: &[&CommandFlag],
+) -> String {
+    let mut lines = vec![format!("complete -c {} -f", name)];
+
+    for flag in flags {
+        lines.push(fish_flag(name, flag, None));
+    }
+
+    for cmd in commands {
+        lines.push(format!(
+            "complete -c {} -n __fish_use_subcommand -a {} -d '{}'",
+            name,
+            cmd.name,
+            escape_fish(cmd.description)
+        ));
+    }
+
+    for cmd in commands {
+        let condition = format!("__fish_seen_subcommand_from {}", cmd.name);
+
+        for flag in &cmd.flags {
+            lines.push(fish_flag(name, flag, Some(&condition)));
+        }
+
+        if let Some(arguments) = fish_arguments(cmd.completion) {
+            lines.push(format!(
+                "complete -c {} -n '{}' -a '{}'",
+                name,
+                condition,
+                escape_fish(&arguments)
+            ));
+        }
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_definition::{CommandExecContext, CommandResult};
+
+    static PLAYER_FLAG: CommandFlag = CommandFlag {
+        name: "player",
+        short_name: Some("p"),
+        description: "the player",
+        has_value: true,
+        completion: ArgCompletion::Command("app players"),
+    };
+
+    static FOLLOW_FLAG: CommandFlag = CommandFlag {
+        name: "follow",
+        short_name: Some("f"),
+        description: "keep going",
+        has_value: false,
+        completion: ArgCompletion::None,
+    };
+
+    fn noop(_: CommandExecContext<String>) -> CommandResult {
+        Ok(())
+    }
+
+    fn new_app() -> App<'static, String> {
+        App::new(String::from("app"), String::from("test app"))
+            .add_flag(&PLAYER_FLAG)
+            .add_command(
+                Command::new_with_usage(
+                    String::from("loop"),
+                    "[none/track]",
+                    "set the [loop]: status",
+                    &noop,
+                )
+                .with_flag(&FOLLOW_FLAG)
+                .with_completion(ArgCompletion::Values(&["none", "track"])),
+            )
+    }
+
+    #[test]
+    fn test_bash_completions() {
+        let script = new_app().completions(Shell::Bash);
+
+        assert!(script.contains("complete -F _app app"));
+        assert!(script.contains(r#"local value_flags=" --player -p ""#));
+        assert!(script.contains(r#"local flags="--player= -p=""#));
+        assert!(script.contains(r#"loop) flags="$flags --follow -f" ;;"#));
+        assert!(script.contains(
+            r#"--player|-p) COMPREPLY=($(compgen -W "$(app players 2>/dev/null)" -- "$cur")); return ;;"#
+        ));
+        assert!(script.contains(r#"loop) COMPREPLY=($(compgen -W "none track""#));
+    }
+
+    #[test]
+    fn test_zsh_completions() {
+        let script = new_app().completions(Shell::Zsh);
+
+        assert!(script.starts_with("#compdef app\n"));
+        assert!(script.contains(r"'loop:set the [loop]\: status'"));
+        assert!(script.contains(
+            r#"'--player=[the player]:player:{compadd -- ${(f)"$(app players 2>/dev/null)"}}'"#
+        ));
+        assert!(script.contains("'--follow[keep going]' \\\n"));
+        assert!(script.contains("'1: :(none track)' \\\n"));
+    }
+
+    #[test]
+    fn test_fish_completions() {
+        let script = new_app().completions(Shell::Fish);
+
+        assert!(script.contains(
+            "complete -c app -l player -s p -x -a '(app players 2>/dev/null)' -d 'the player'"
+        ));
+        assert!(script.contains(
+            "complete -c app -n '__fish_seen_subcommand_from loop' -l follow -s f -d 'keep going'"
+        ));
+        assert!(script
+            .contains("complete -c app -n '__fish_seen_subcommand_from loop' -a 'none track'"));
+    }
+
+    #[test]
+    fn test_shell_from_str() {
+        assert_eq!("zsh".parse::<Shell>(), Ok(Shell::Zsh));
+        assert!("powershell".parse::<Shell>().is_err());
+    }
+}
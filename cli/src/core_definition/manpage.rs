@@ -0,0 +1,160 @@
+use super::CommandNameConstraints;
+use super::{App, CommandFlag};
+
+impl<'a, CommandName> App<'a, CommandName>
+where
+    CommandName: CommandNameConstraints,
+{
+    /// A man page, in roff, with every flag and command
+    pub fn manpage(&self) -> String {
+        let mut lines = vec![
+            format!(".TH {} 1", escape_roff(&self.name.to_uppercase())),
+            String::from(".SH NAME"),
+            format!(
+                "{} \\- {}",
+                escape_roff(&self.name),
+                escape_roff(&self.description)
+            ),
+            String::from(".SH SYNOPSIS"),
+            format!(".B {}", escape_roff(&self.name)),
+            String::from("[\\fIflags\\fR] \\fIcommand\\fR [\\fIargs\\fR]"),
+            String::from(".SH OPTIONS"),
+        ];
+
+        for flag in &self.flags {
+            lines.push(String::from(".TP"));
+            lines.push(format_flag(flag));
+            lines.push(escape_roff(flag.description));
+        }
+
+        lines.push(String::from(".SH COMMANDS"));
+
+        for (title, commands) in self.grouped_commands() {
+            lines.push(format!(".SS {}", escape_roff(title)));
+
+            for cmd in commands {
+                lines.push(String::from(".TP"));
+                if cmd.usage.is_empty() {
+                    lines.push(format!("\\fB{}\\fR", escape_roff(&cmd.name.to_string())));
+                } else {
+                    lines.push(format!(
+                        "\\fB{}\\fR {}",
+                        escape_roff(&cmd.name.to_string()),
+                        escape_roff(cmd.usage)
+                    ));
+                }
+                lines.push(escape_roff(cmd.description));
+
+                for flag in &cmd.flags {
+                    lines.push(String::from(".RS"));
+                    lines.push(format!(
+                        "{}: {}",
+                        format_flag(flag),
+                        escape_roff(flag.description)
+                    ));
+                    lines.push(String::from(".RE"));
+                }
+
+                for (name, description) in &cmd.args {
+                    lines.push(String::from(".RS"));
+                    lines.push(format!(
+                        "\\fI{}\\fR: {}",
+                        escape_roff(name),
+                        escape_roff(description)
+                    ));
+                    lines.push(String::from(".RE"));
+                }
+            }
+        }
+
+        let examples = self
+            .ordered_commands()
+            .into_iter()
+            .flat_map(|cmd| cmd.examples.iter())
+            .collect::<Vec<_>>();
+
+        if !examples.is_empty() {
+            lines.push(String::from(".SH EXAMPLES"));
+            for (command_line, description) in examples {
+                lines.push(String::from(".TP"));
+                lines.push(format!("\\fB{}\\fR", escape_roff(command_line)));
+                lines.push(escape_roff(description));
+            }
+        }
+
+        if !self.exit_codes.is_empty() {
+            lines.push(String::from(".SH EXIT STATUS"));
+            for (code, description) in &self.exit_codes {
+                lines.push(String::from(".TP"));
+                lines.push(format!("\\fB{}\\fR", code));
+                lines.push(escape_roff(description));
+            }
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+fn format_flag(flag: &CommandFlag) -> String {
+    let value = if flag.has_value { "=\\fIvalue\\fR" } else { "" };
+    let short_name = match flag.short_name {
+        Some(short_name) => format!("\\fB\\-{}\\fR, ", escape_roff(short_name)),
+        None => String::new(),
+    };
+
+    format!(
+        "{}\\fB\\-\\-{}\\fR{}",
+        short_name,
+        escape_roff(flag.name),
+        value
+    )
+}
+
+// backslashes start escapes, dots and quotes at the start of a line are
+// requests, and "-" may be rendered as an hyphen
+fn escape_roff(s: &str) -> String {
+    let escaped = s.replace('\\', "\\e").replace('-', "\\-");
+
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_definition::{Command, CommandExecContext, CommandResult};
+
+    fn noop(_: CommandExecContext<String>) -> CommandResult {
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_roff() {
+        assert_eq!(escape_roff("play-pause"), "play\\-pause");
+        assert_eq!(escape_roff(".5 volume"), "\\&.5 volume");
+        assert_eq!(escape_roff("a\\b"), "a\\eb");
+    }
+
+    #[test]
+    fn test_manpage() {
+        let app = App::new(String::from("app"), String::from("test app"))
+            .add_group("Playback")
+            .add_command(
+                Command::new_with_usage(String::from("volume"), "[value]", "the volume", &noop)
+                    .with_arg("value", "between 0 and 1")
+                    .with_example("app volume 0.5", "half the volume"),
+            )
+            .add_exit_code(2, "invalid argument");
+
+        let manpage = app.manpage();
+
+        assert!(manpage.starts_with(".TH APP 1\n.SH NAME\napp \\- test app\n"));
+        assert!(manpage.contains(".SS Playback\n.TP\n\\fBvolume\\fR [value]\nthe volume\n"));
+        assert!(manpage.contains(".SH EXAMPLES\n.TP\n\\fBapp volume 0.5\\fR\nhalf the volume\n"));
+        assert!(manpage.contains(".SH EXIT STATUS\n.TP\n\\fB2\\fR\ninvalid argument\n"));
+    }
+}
@@ -1,7 +1,11 @@
 mod app;
 mod args;
 mod cmd;
+mod completions;
+mod manpage;
+mod suggest;
 
-pub use app::App;
+pub use app::{App, RunError};
 pub use args::ParsedArgs;
 pub use cmd::*;
+pub use completions::Shell;
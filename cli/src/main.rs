@@ -1,75 +1,193 @@
 mod cmds;
 mod core_definition;
 
-use cmds::CommandName;
-use core_definition::{App, Command, CommandFlag};
+use cmds::{CommandName, ErrorCode};
+use core_definition::{App, ArgCompletion, Command, CommandFlag, RunError};
 use once_cell::sync::Lazy;
 
+// the player names are only known at runtime
+const PLAYERS_COMPLETION: ArgCompletion = ArgCompletion::Command("rstroller completions players");
+
 static PLAYER_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
     CommandFlag::new(
         "player",
         Some("p"),
-        "Specify the player for a single command",
+        "Specify the player for a single command, by bus name, identity, alias or glob",
+        true,
+    )
+    .with_completion(PLAYERS_COMPLETION)
+});
+
+static PLAYERS_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "players",
+        None,
+        "Run the command on every player matching one of the comma separated patterns (eg: spotify,firefox.*)",
+        true,
+    )
+    .with_completion(PLAYERS_COMPLETION)
+});
+
+static ALL_PLAYERS_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "all-players",
+        None,
+        "Run the command on every player",
+        false,
+    )
+});
+
+static OUTPUT_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "output",
+        Some("o"),
+        "Output format: waybar, polybar, i3blocks, i3bar, eww or tmux",
         true,
     )
+    .with_completion(ArgCompletion::Values(&[
+        "waybar", "polybar", "i3blocks", "i3bar", "eww", "tmux",
+    ]))
+});
+
+static ALL_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "all",
+        Some("a"),
+        "Show every player, not only the preferred one",
+        false,
+    )
+});
+
+static FOLLOW_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "follow",
+        Some("f"),
+        "Keep running and print the value every time it changes",
+        false,
+    )
+});
+
+static ANY_PLAYER_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "any-player",
+        None,
+        "If the player can't open it, open it in the first player that can",
+        false,
+    )
+});
+
+static ART_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "art",
+        None,
+        "Show the album art, with kitty or sixel graphics when the terminal supports them",
+        false,
+    )
+});
+
+static ART_FORMAT_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "art-format",
+        None,
+        "How to draw the album art: kitty, sixel or blocks, guessed from the terminal by default",
+        true,
+    )
+    .with_completion(ArgCompletion::Values(&["kitty", "sixel", "blocks"]))
+});
+
+static STOP_ON_ERROR_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "stop-on-error",
+        None,
+        "Don't run the commands left once one fails",
+        false,
+    )
+});
+
+static TIMEOUT_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "timeout",
+        Some("t"),
+        "Give up after this long (eg: 30s or 1m), and exit with an error",
+        true,
+    )
+});
+
+static JSON_FLAG: Lazy<CommandFlag> = Lazy::new(|| {
+    CommandFlag::new(
+        "json",
+        Some("j"),
+        "Print the output, or the error, as a single JSON document",
+        false,
+    )
 });
 
 fn main() {
-    let mut app = new_app();
-    if app.run_cmd().is_none() {
-        println!("Unknown command");
-        app.help();
+    let app = new_app();
+
+    let args = match app.arg_parser.parse() {
+        Ok(args) => args,
+        Err(err) => {
+            // the args couldn't be parsed, so the flag is looked for by hand
+            let as_json = std::env::args().any(|arg| arg == "--json" || arg == "-j");
+            invalid_args(as_json, RunError::InvalidArgs(err));
+        }
+    };
+    let as_json = args.flags.contains_key("json");
+
+    match app.run_args(args) {
+        Ok(_) => {}
+        Err(RunError::NoCommand) => cmds::print_raw(app.help_text()),
+        Err(RunError::Failed(err)) => {
+            cmds::print_command_error(as_json, &err);
+            std::process::exit(cmds::error_code(&err).exit_code());
+        }
+        Err(err) => invalid_args(as_json, err),
     }
 }
 
+fn invalid_args(as_json: bool, err: RunError) -> ! {
+    cmds::print_error(as_json, ErrorCode::InvalidArgument, &err);
+    if !as_json {
+        eprintln!("Run \"rstroller help\" to see every command and flag");
+    }
+    std::process::exit(ErrorCode::InvalidArgument.exit_code());
+}
+
 fn new_app() -> App<'static, CommandName> {
-    App::new(
+    let app = App::new(
         String::from("rstroller"),
         String::from("player controller for MPRIS"),
     )
     .add_flag(&PLAYER_FLAG)
-    .add_command(Command::new(
-        CommandName::Help,
-        "show command usage",
-        &cmds::help_cmd,
-    ))
-    .add_command(Command::new(
-        CommandName::List,
-        "list available MPRIS players",
-        &cmds::list_players_cmd,
-    ))
-    .add_command(Command::new_with_usage(
-        CommandName::SetPlayer,
-        "<player>",
-        "set the preferred player",
-        &cmds::set_preferred_player_cmd,
-    ))
-    .add_command(Command::new_with_usage(
-        CommandName::ScrollPlayer,
-        "<up/down>",
-        "cycle the preferred player",
-        &cmds::scroll_preferred_player_cmd,
-    ))
-    .add_command(Command::new(
-        CommandName::Status,
-        "get the playback status of the player",
-        &cmds::status_cmd,
-    ))
+    .add_flag(&PLAYERS_FLAG)
+    .add_flag(&ALL_PLAYERS_FLAG)
+    .add_flag(&JSON_FLAG)
+    .add_group("Playback")
     .add_command(Command::new(
         CommandName::Play,
         r#"send the "play" command to the player"#,
         &cmds::play_cmd,
     ))
-    .add_command(Command::new(
-        CommandName::Pause,
-        r#"send the "pause" command to the player"#,
-        &cmds::pause_cmd,
-    ))
-    .add_command(Command::new(
-        CommandName::PlayPause,
-        r#"send the "play/pause" command to the player"#,
-        &cmds::play_pause_cmd,
-    ))
+    .add_command(
+        Command::new(
+            CommandName::Pause,
+            r#"send the "pause" command to the player"#,
+            &cmds::pause_cmd,
+        )
+        .with_example("rstroller pause --all-players", "pause every player"),
+    )
+    .add_command(
+        Command::new(
+            CommandName::PlayPause,
+            r#"send the "play/pause" command to the player"#,
+            &cmds::play_pause_cmd,
+        )
+        .with_example(
+            "rstroller play-pause --player=org.mpris.MediaPlayer2.spotify",
+            "toggle spotify, no matter which player is the preferred one",
+        ),
+    )
     .add_command(Command::new(
         CommandName::Stop,
         r#"send the "stop" command to the player"#,
@@ -85,49 +203,291 @@ fn new_app() -> App<'static, CommandName> {
         r#"send the "previous" command to the player"#,
         &cmds::previous_cmd,
     ))
-    .add_command(Command::new_with_usage(
-        CommandName::Metadata,
-        "[key]",
-        "get all or a specific metadata value from the player",
-        &cmds::metadata_cmd,
-    ))
-    .add_command(Command::new_with_usage(
-        CommandName::Position,
-        "[value/offset+]",
-        "get or set the playback position IN MILLISECONDS of the player, either as a absolute value (1000) or a relative value (eg: 500- or 1200+)",
-        &cmds::position_cmd,
-    ))
-    .add_command(Command::new(
-        CommandName::Show,
-        "show some information about the player",
-        &cmds::show_cmd,
-    ))
-    .add_command(Command::new_with_usage(
-        CommandName::Loop,
-        "[none/track/playlist]",
-        r#"get or set the loop status of the player"#,
-        &cmds::loop_cmd,
-    ))
-    .add_command(Command::new_with_usage(
-        CommandName::Shuffle,
-        "[true/false]",
-        r#"get or set the loop status of the player"#,
-        &cmds::shuffle_cmd,
-    ))
-    .add_command(Command::new_with_usage(
-        CommandName::Volume,
-        "[value/offset+]",
-        "get or set the player volume, either as a absolute value (0.5) or a relative value (eg: 0.05- or 0.1+)",
-        &cmds::volume_cmd,
-    ))
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Position,
+            "[position/offset+]",
+            "get the playback position IN MILLISECONDS of the player, or set it like seek, where a bare number is in milliseconds",
+            &cmds::position_cmd,
+        )
+        .with_flag(&FOLLOW_FLAG)
+        .with_arg("position", "the new position, in milliseconds (5000) or as in seek (1:23, 50%)")
+        .with_arg("offset+/offset-", "move forward or backward, in milliseconds (500- or -500) or as in seek (30s+)")
+        .with_example("rstroller position", "print the position, in milliseconds")
+        .with_example("rstroller position 5000+", "skip 5 seconds"),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Seek,
+            "<position/offset+>",
+            "move to a position of the track, clamped to its length, and print it",
+            &cmds::seek_cmd,
+        )
+        .with_arg("position", "a time (1:23, 1h2m, 30s, 500ms, or 90 seconds) or a percentage of the track (50%)")
+        .with_arg("offset+/offset-", "move forward or backward, like 30s+, 10s- or -10s")
+        .with_example("rstroller seek 1:23", "go to 1 minute and 23 seconds")
+        .with_example("rstroller seek 10s-", "go back 10 seconds")
+        .with_example("rstroller seek 50%", "go to the middle of the track"),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Open,
+            "<uri/path>",
+            "play a URI or a file in the player, if its supported URI schemes and mime types allow it",
+            &cmds::open_cmd,
+        )
+        .with_flag(&ANY_PLAYER_FLAG)
+        .with_arg("uri", "a URI, like https://example.com/stream.mp3")
+        .with_arg("path", "a file, relative to the current directory or not")
+        .with_example("rstroller open song.flac", "play a local file")
+        .with_example(
+            "rstroller open https://example.com/stream.mp3 --any-player",
+            "play a stream in any player that supports it",
+        ),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Volume,
+            "[value/offset+/up/down/mute/unmute/toggle-mute]",
+            "get or set the player volume, as a value (0.5 or 40%), an offset (0.1+ or 5%-) or a change in decibels (-3dB), kept inside the configured limits",
+            &cmds::volume_cmd,
+        )
+        .with_flag(&FOLLOW_FLAG)
+        .with_completion(ArgCompletion::Values(&[
+            "up",
+            "down",
+            "mute",
+            "unmute",
+            "toggle-mute",
+        ]))
+        .with_arg("value", "the new volume, 1.0 or 100% is the full volume")
+        .with_arg("offset+/offset-", "raise or lower the volume, like 0.05+ or 5%-")
+        .with_arg("dB", "raise or lower the volume by some decibels, like -3dB or +3dB")
+        .with_arg("up/down", "raise or lower the volume by the configured step")
+        .with_arg(
+            "mute/unmute/toggle-mute",
+            "mute the player, or bring back the volume it had before",
+        )
+        .with_example("rstroller volume 40%", "set the volume to 40%")
+        .with_example("rstroller volume 0.05-", "lower the volume by 5%")
+        .with_example(
+            "rstroller volume 0.1- --players=firefox.*,chromium.*",
+            "lower the volume of every browser",
+        ),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Loop,
+            "[none/track/playlist]",
+            r#"get or set the loop status of the player"#,
+            &cmds::loop_cmd,
+        )
+        .with_flag(&FOLLOW_FLAG)
+        .with_completion(ArgCompletion::Values(&["none", "track", "playlist"]))
+        .with_arg("status", "none, track or playlist")
+        .with_example("rstroller loop track", "repeat the current track"),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Shuffle,
+            "[true/false]",
+            r#"get or set the shuffle status of the player"#,
+            &cmds::shuffle_cmd,
+        )
+        .with_flag(&FOLLOW_FLAG)
+        .with_completion(ArgCompletion::Values(&["true", "false"]))
+        .with_arg("status", "true or false"),
+    )
     .add_command(Command::new(
         CommandName::Raise,
         r#"send the "raise" command to the player"#,
         &cmds::raise_cmd,
     ))
+    .add_group("Information")
+    .add_command(
+        Command::new(
+            CommandName::Status,
+            "get the playback status of the player",
+            &cmds::status_cmd,
+        )
+        .with_flag(&FOLLOW_FLAG)
+        .with_example(
+            "rstroller status --follow",
+            "print the status every time it changes",
+        ),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Metadata,
+            "[key]",
+            "get all or a specific metadata value from the player",
+            &cmds::metadata_cmd,
+        )
+        .with_flag(&FOLLOW_FLAG)
+        .with_arg("key", "a metadata key, like xesam:title")
+        .with_example("rstroller metadata xesam:title", "print the track title"),
+    )
+    .add_command(
+        Command::new(
+            CommandName::Show,
+            "show some information about the player",
+            &cmds::show_cmd,
+        )
+        .with_flag(&ART_FLAG)
+        .with_flag(&ART_FORMAT_FLAG)
+        .with_example("rstroller show --art", "show the track with its album art")
+        .with_example(
+            "rstroller show --art --art-format=blocks",
+            "draw the album art with colored blocks, for any terminal",
+        ),
+    )
+    .add_group("Players")
     .add_command(Command::new(
-        CommandName::Waybar,
-        "still haven't figured out yet",
-        &cmds::waybar_cmd,
+        CommandName::List,
+        "list available MPRIS players",
+        &cmds::list_players_cmd,
+    ))
+    .add_command(
+        Command::new_with_usage(
+            CommandName::SetPlayer,
+            "<player>",
+            "set the preferred player",
+            &cmds::set_preferred_player_cmd,
+        )
+        .with_completion(PLAYERS_COMPLETION)
+        .with_arg(
+            "player",
+            "the bus name of the player, as shown by list, or a part of it, its identity or an alias",
+        )
+        .with_example(
+            "rstroller set-player spotify",
+            "send the next commands to spotify",
+        ),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::ScrollPlayer,
+            "<up/down>",
+            "cycle the preferred player",
+            &cmds::scroll_preferred_player_cmd,
+        )
+        .with_completion(ArgCompletion::Values(&["up", "down"]))
+        .with_arg("direction", "up for the previous player, down for the next one"),
+    )
+    .add_group("Integrations")
+    .add_command(
+        Command::new(
+            CommandName::Waybar,
+            "keep printing the player status for waybar, or other bars with --output",
+            &cmds::waybar_cmd,
+        )
+        .with_flag(&OUTPUT_FLAG)
+        .with_flag(&ALL_FLAG)
+        .with_example(
+            "rstroller waybar --output=polybar --all",
+            "show every player in polybar",
+        ),
+    )
+    .add_command(
+        Command::new(
+            CommandName::Tui,
+            "control the player from a full screen terminal UI, which also works over SSH",
+            &cmds::tui_cmd,
+        )
+        .with_example(
+            "rstroller tui --player spotify",
+            "control spotify, j/k and enter switch to another player",
+        ),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Batch,
+            "[file]",
+            "run many commands, one per line or separated by ;, from a file or stdin, in a single process",
+            &cmds::batch_cmd,
+        )
+        .with_flag(&STOP_ON_ERROR_FLAG)
+        .with_arg("file", "the commands to run, stdin when missing or -")
+        .with_example(
+            "echo 'volume 0.3; next; position 30000' | rstroller batch",
+            "run the commands one after another, printing one result for each",
+        )
+        .with_example(
+            "rstroller batch macro.txt --player spotify --stop-on-error",
+            "run the commands of a file on spotify, until one of them fails",
+        ),
+    )
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Wait,
+            "<condition>",
+            "block until the condition holds for the player, or a player shows up",
+            &cmds::wait_cmd,
+        )
+        .with_flag(&TIMEOUT_FLAG)
+        .with_completion(ArgCompletion::Values(&[
+            "status=playing",
+            "status=paused",
+            "status=stopped",
+            "track-change",
+        ]))
+        .with_arg("status=<status>", "the player is playing, paused or stopped")
+        .with_arg("track-change", "the player moved to another track")
+        .with_arg("player=<player>", "a player matching the name, like --player, is running")
+        .with_arg("position>time/position<time", "the position is past or before a time, like 60s or 1:30")
+        .with_example(
+            "rstroller wait player=spotify --timeout=10s && rstroller seek 1:00 --player spotify",
+            "seek once spotify shows up",
+        )
+        .with_example(
+            "rstroller wait track-change; notify-send \"$(rstroller metadata xesam:title)\"",
+            "show the title of the next track",
+        ),
+    )
+    .add_command(Command::new(
+        CommandName::Daemon,
+        "run the policies enabled in the config file, like exclusive playback",
+        &cmds::daemon_cmd,
     ))
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Completions,
+            "<bash/zsh/fish>",
+            "print the completion script for the shell",
+            &cmds::completions_cmd,
+        )
+        .with_completion(ArgCompletion::Values(&["bash", "zsh", "fish"]))
+        .with_example(
+            "rstroller completions fish > ~/.config/fish/completions/rstroller.fish",
+            "install the fish completions",
+        ),
+    )
+    .add_group("Help")
+    .add_command(
+        Command::new_with_usage(
+            CommandName::Help,
+            "[command]",
+            "show the usage of every command, or the arguments and examples of one",
+            &cmds::help_cmd,
+        )
+        .with_completion(ArgCompletion::Command("rstroller completions commands")),
+    )
+    .add_command(
+        Command::new(
+            CommandName::Manpage,
+            "print the man page, in roff",
+            &cmds::manpage_cmd,
+        )
+        .with_example(
+            "rstroller manpage | man -l -",
+            "read the man page",
+        ),
+    );
+
+    // scripts can tell why a command failed from its exit status
+    ErrorCode::ALL
+        .iter()
+        .fold(app.add_exit_code(0, "success"), |app, code| {
+            app.add_exit_code(code.exit_code(), code.description())
+        })
 }
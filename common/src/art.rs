@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Result as AnyResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ART_CACHE_DIR: &str = "/tmp/rstroller";
+
+pub enum ArtSource {
+    Local(PathBuf),
+    Remote(String),
+}
+
+/// Figure out where the art from the `mpris:artUrl` metadata lives
+pub fn resolve_art_url(art_url: &str) -> Option<ArtSource> {
+    if let Some(path) = art_url.strip_prefix("file://") {
+        let path = urlencoding::decode(path).map(|p| p.into_owned());
+        Some(ArtSource::Local(PathBuf::from(
+            path.unwrap_or_else(|_| art_url.replace("file://", "")),
+        )))
+    } else if art_url.starts_with("http://") || art_url.starts_with("https://") {
+        Some(ArtSource::Remote(art_url.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Path where a remote art is (or will be) cached
+pub fn get_cached_art_path(url: &str) -> PathBuf {
+    Path::new(ART_CACHE_DIR).join(urlencoding::encode(url).as_ref())
+}
+
+/// Download a remote art into `dist_path`.
+///
+/// The file is only moved into place once it's fully downloaded, so a failed
+/// download doesn't leave a broken file in the cache.
+pub fn download_art(url: &str, dist_path: &Path) -> AnyResult<()> {
+    if let Some(parent) = dist_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let url = reqwest::Url::parse(url)?;
+    let mut resp = reqwest::blocking::get(url)?.error_for_status()?;
+
+    let mut tmp_path = dist_path.as_os_str().to_owned();
+    tmp_path.push(".part");
+
+    let mut file = fs::File::create(&tmp_path)?;
+    std::io::copy(&mut resp, &mut file)?;
+    fs::rename(tmp_path, dist_path)?;
+
+    Ok(())
+}
+
+/// Get a local path to the art, downloading it if needed (blocking)
+pub fn fetch_art(art_url: &str) -> AnyResult<PathBuf> {
+    match resolve_art_url(art_url) {
+        Some(ArtSource::Local(path)) => Ok(path),
+        Some(ArtSource::Remote(url)) => {
+            let path = get_cached_art_path(&url);
+            if !path.exists() {
+                download_art(&url, &path)?;
+            }
+            Ok(path)
+        }
+        None => Err(anyhow!("unsupported art url: {}", art_url)),
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result as AnyResult};
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, io, path::PathBuf, time::Duration};
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub exclusive: ExclusiveConfig,
+    pub ducking: DuckingConfig,
+    pub notifications: NotificationsConfig,
+    pub waybar: WaybarConfig,
+    pub volume: VolumeConfig,
+    /// Short names for players, like `music = "spotify"`, accepted wherever
+    /// a player name is
+    pub aliases: HashMap<String, String>,
+}
+
+/// Pause every other player when one of them starts playing
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExclusiveConfig {
+    pub enabled: bool,
+    /// Players that are never paused and never pause the others
+    pub except: Vec<String>,
+    /// Resume the paused players once the one that interrupted them stops
+    pub resume: bool,
+}
+
+/// Lower the volume of the other players while a "voice" player is playing
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DuckingConfig {
+    pub enabled: bool,
+    /// Players that have priority, like video calls or podcasts
+    pub voice: Vec<String>,
+    /// The volume of the other players is multiplied by this
+    pub ratio: f64,
+}
+
+impl Default for DuckingConfig {
+    fn default() -> Self {
+        DuckingConfig {
+            enabled: false,
+            voice: vec![],
+            ratio: 0.3,
+        }
+    }
+}
+
+/// Show a desktop notification when the track changes
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WaybarConfig {
+    /// Show the elapsed or remaining time of the track
+    pub time: TimeDisplay,
+    /// How often, in seconds, the position is refreshed while playing
+    pub interval: f64,
+    /// Shown between the players, with the --all flag
+    pub separator: String,
+    /// Shown when there's no player
+    pub no_player_text: String,
+    /// The tooltip when there's no player, for the formats that have one
+    pub no_player_tooltip: String,
+    pub marquee: MarqueeConfig,
+}
+
+impl Default for WaybarConfig {
+    fn default() -> Self {
+        WaybarConfig {
+            time: TimeDisplay::None,
+            interval: 1.0,
+            separator: String::from("  "),
+            no_player_text: String::from("Silence"),
+            no_player_tooltip: String::from("Nothing playing"),
+            marquee: MarqueeConfig::default(),
+        }
+    }
+}
+
+impl WaybarConfig {
+    pub fn interval(&self) -> Duration {
+        secs_to_duration(self.interval)
+    }
+}
+
+// between 0.1s and an hour, "inf" and "nan" are valid TOML floats
+fn secs_to_duration(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs.clamp(0.1, 3600.0)).unwrap_or(Duration::from_millis(100))
+}
+
+/// Scroll long titles instead of truncating them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MarqueeConfig {
+    pub enabled: bool,
+    /// How many columns are visible at once
+    pub width: usize,
+    /// How long, in seconds, each step takes
+    pub speed: f64,
+    /// How long, in seconds, the text stays still at the start
+    pub pause: f64,
+    /// Shown between the end and the start of the text
+    pub separator: String,
+}
+
+impl Default for MarqueeConfig {
+    fn default() -> Self {
+        MarqueeConfig {
+            enabled: false,
+            width: 40,
+            speed: 0.5,
+            pause: 2.0,
+            separator: String::from(" | "),
+        }
+    }
+}
+
+impl MarqueeConfig {
+    pub fn speed(&self) -> Duration {
+        secs_to_duration(self.speed)
+    }
+}
+
+/// Limits of the volume, for every way of changing it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct VolumeConfig {
+    pub min: f64,
+    /// Can be over 1.0, for the players that amplify the sound
+    pub max: f64,
+    /// How much `volume up` and `volume down` change it
+    pub step: f64,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        VolumeConfig {
+            min: 0.0,
+            max: 1.0,
+            step: 0.05,
+        }
+    }
+}
+
+impl VolumeConfig {
+    pub fn clamp(&self, volume: f64) -> f64 {
+        volume.max(self.min).min(self.max.max(self.min))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeDisplay {
+    #[default]
+    None,
+    Elapsed,
+    Remaining,
+}
+
+/// Path to the config file, `$XDG_CONFIG_HOME/rstroller/config.toml` falling
+/// back to `~/.config/rstroller/config.toml`
+pub fn get_config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join("rstroller").join("config.toml"))
+}
+
+/// Load the config file, a missing file is not an error: the default config
+/// is used instead
+pub fn load_config() -> AnyResult<Config> {
+    let path = match get_config_path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)
+            .with_context(|| format!("couldn't parse the config file {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err).context("couldn't read the config file"),
+    }
+}
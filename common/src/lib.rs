@@ -1,3 +1,6 @@
+pub mod art;
+pub mod config;
 pub mod err;
 pub mod player;
 pub mod utils;
+pub mod volume;
@@ -0,0 +1,310 @@
+use crate::config::load_config;
+use anyhow::{Context, Result as AnyResult};
+use mpris::{Player, PlayerFinder};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::rc::Rc;
+
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+thread_local! {
+    // set by `share_connection`, otherwise every lookup has its own connection
+    static SHARED_FINDER: RefCell<Option<Rc<PlayerFinder>>> = const { RefCell::new(None) };
+}
+
+// if a player, by bus name and identity, matches
+type Matcher<'a> = &'a dyn Fn(&str, &str) -> bool;
+
+/// More than one player matches the name, so none of them is picked
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbiguousPlayer {
+    pub name: String,
+    /// Bus names of the matching players
+    pub matches: Vec<String>,
+}
+
+impl Display for AmbiguousPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Player {} is ambiguous, it matches {}",
+            self.name,
+            self.matches.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for AmbiguousPlayer {}
+
+fn short_name(bus_name: &str) -> &str {
+    bus_name.strip_prefix(BUS_NAME_PREFIX).unwrap_or(bus_name)
+}
+
+/// The player part of the bus name, eg: `firefox` for
+/// `org.mpris.MediaPlayer2.firefox.instance_1_84`
+pub fn player_part(bus_name: &str) -> &str {
+    short_name(bus_name).split('.').next().unwrap_or(bus_name)
+}
+
+/// If the player matches the pattern, ignoring the case.
+///
+/// The pattern is compared to the bus name, the bus name without the MPRIS
+/// prefix (`firefox.instance_1_84`), the player part of it (`firefox`) and
+/// the identity, and may use `*` and `?` as wildcards.
+pub fn matches_pattern(bus_name: &str, identity: &str, pattern: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+
+    [
+        bus_name,
+        short_name(bus_name),
+        player_part(bus_name),
+        identity,
+    ]
+    .iter()
+    .any(|name| glob_match(&pattern, &name.to_lowercase()))
+}
+
+/// The pattern an alias from the config stands for, or the name itself
+pub fn resolve_alias<'a>(name: &'a str, aliases: &'a HashMap<String, String>) -> &'a str {
+    aliases.get(name).map(String::as_str).unwrap_or(name)
+}
+
+/// Pick the player called `name`, from the most to the least strict way of
+/// matching it: the bus name, the bus name without the MPRIS prefix, its
+/// player part or the identity, a glob, a prefix of those and a suffix of the
+/// bus name. The first way that matches anything wins, and it must match a
+/// single player.
+///
+/// `players` are the bus names and identities, the index of the player is
+/// returned.
+pub fn select_player(
+    name: &str,
+    players: &[(&str, &str)],
+) -> Result<Option<usize>, AmbiguousPlayer> {
+    // everything starts with ""
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    let lower_name = name.to_lowercase();
+    let is_glob = name.contains(['*', '?']);
+
+    let ways: [Matcher; 5] = [
+        &|bus_name, _| bus_name == name,
+        &|bus_name, identity| {
+            short_name(bus_name).to_lowercase() == lower_name
+                || player_part(bus_name).to_lowercase() == lower_name
+                || identity.to_lowercase() == lower_name
+        },
+        &|bus_name, identity| is_glob && matches_pattern(bus_name, identity, name),
+        &|bus_name, identity| {
+            short_name(bus_name).to_lowercase().starts_with(&lower_name)
+                || identity.to_lowercase().starts_with(&lower_name)
+        },
+        &|bus_name, _| bus_name.to_lowercase().ends_with(&lower_name),
+    ];
+
+    for matches in ways {
+        let found = players
+            .iter()
+            .enumerate()
+            .filter(|(_, (bus_name, identity))| matches(bus_name, identity))
+            .collect::<Vec<_>>();
+
+        match found.as_slice() {
+            [] => continue,
+            [(idx, _)] => return Ok(Some(*idx)),
+            _ => {
+                return Err(AmbiguousPlayer {
+                    name: name.to_string(),
+                    matches: found
+                        .iter()
+                        .map(|(_, (bus_name, _))| bus_name.to_string())
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn load_aliases() -> AnyResult<HashMap<String, String>> {
+    Ok(load_config()
+        .context("couldn't load the player aliases")?
+        .aliases)
+}
+
+/// Make the player lookups of this thread reuse a single D-Bus connection,
+/// for many commands in a row. Long running loops shouldn't use it, the
+/// signals nobody reads pile up in the connection.
+pub fn share_connection() -> AnyResult<()> {
+    let finder = Rc::new(PlayerFinder::new()?);
+    SHARED_FINDER.with(|shared| *shared.borrow_mut() = Some(finder));
+    Ok(())
+}
+
+/// A finder on the shared connection, or on a new one
+pub fn player_finder() -> AnyResult<Rc<PlayerFinder>> {
+    match SHARED_FINDER.with(|shared| shared.borrow().clone()) {
+        Some(finder) => Ok(finder),
+        None => Ok(Rc::new(PlayerFinder::new()?)),
+    }
+}
+
+/// The running player called `name`, see `select_player`. Aliases from the
+/// config are accepted too, and an ambiguous name is an `AmbiguousPlayer`
+/// error.
+pub fn find_player(name: &str) -> AnyResult<Option<Player>> {
+    let finder = player_finder()?;
+    let mut players = finder.find_all()?;
+
+    // the exact bus name is the common case, no need for the config
+    if let Some(idx) = players.iter().position(|p| p.bus_name() == name) {
+        return Ok(Some(players.swap_remove(idx)));
+    }
+
+    let aliases = load_aliases()?;
+    let name = resolve_alias(name, &aliases);
+
+    let names = players
+        .iter()
+        .map(|player| (player.bus_name(), player.identity()))
+        .collect::<Vec<_>>();
+
+    match select_player(name, &names)? {
+        Some(idx) => Ok(Some(players.swap_remove(idx))),
+        None => Ok(None),
+    }
+}
+
+/// Every running player that matches at least one of the patterns, which
+/// may be aliases
+pub fn find_players_matching(patterns: &[&str]) -> AnyResult<Vec<Player>> {
+    let aliases = load_aliases()?;
+    let patterns = patterns
+        .iter()
+        .map(|pattern| resolve_alias(pattern, &aliases))
+        .collect::<Vec<_>>();
+
+    let finder = player_finder()?;
+
+    Ok(finder
+        .find_all()?
+        .into_iter()
+        .filter(|player| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(player.bus_name(), player.identity(), pattern))
+        })
+        .collect())
+}
+
+pub fn find_all_players() -> AnyResult<Vec<Player>> {
+    Ok(player_finder()?.find_all()?)
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // matches[j]: if the pattern so far matches the first j chars of the text
+    let mut matches = vec![false; text.len() + 1];
+    matches[0] = true;
+
+    for c in pattern {
+        let mut next = vec![false; text.len() + 1];
+
+        for j in 0..=text.len() {
+            next[j] = match c {
+                '*' => matches[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matches[j - 1],
+                c => j > 0 && matches[j - 1] && text[j - 1] == c,
+            };
+        }
+
+        matches = next;
+    }
+
+    matches[text.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYERS: [(&str, &str); 4] = [
+        ("org.mpris.MediaPlayer2.spotify", "Spotify"),
+        (
+            "org.mpris.MediaPlayer2.firefox.instance_1_84",
+            "Mozilla Firefox",
+        ),
+        (
+            "org.mpris.MediaPlayer2.firefox.instance_1_97",
+            "Mozilla Firefox",
+        ),
+        ("org.mpris.MediaPlayer2.mpv", "mpv"),
+    ];
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("spotify", "spotify"));
+        assert!(glob_match("firefox.*", "firefox.instance_1_42"));
+        assert!(glob_match("*fox*", "firefox"));
+        assert!(glob_match("mp?", "mpv"));
+        assert!(!glob_match("firefox.*", "firefox"));
+        assert!(!glob_match("spot", "spotify"));
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        let bus_name = "org.mpris.MediaPlayer2.firefox.instance_1_42";
+
+        assert!(matches_pattern(bus_name, "Mozilla Firefox", bus_name));
+        assert!(matches_pattern(bus_name, "Mozilla Firefox", "firefox.*"));
+        assert!(matches_pattern(
+            bus_name,
+            "Mozilla Firefox",
+            "mozilla firefox"
+        ));
+        assert!(matches_pattern(bus_name, "Mozilla Firefox", "firefox"));
+        assert!(!matches_pattern(bus_name, "Mozilla Firefox", "spotify"));
+    }
+
+    #[test]
+    fn test_select_player() {
+        assert_eq!(
+            select_player("org.mpris.MediaPlayer2.mpv", &PLAYERS),
+            Ok(Some(3))
+        );
+        assert_eq!(select_player("spotify", &PLAYERS), Ok(Some(0)));
+        assert_eq!(select_player("Spot", &PLAYERS), Ok(Some(0)));
+        assert_eq!(select_player("*_84", &PLAYERS), Ok(Some(1)));
+        assert_eq!(select_player("instance_1_97", &PLAYERS), Ok(Some(2)));
+        assert_eq!(select_player("vlc", &PLAYERS), Ok(None));
+        assert_eq!(select_player("", &PLAYERS), Ok(None));
+    }
+
+    #[test]
+    fn test_select_ambiguous_player() {
+        let err = select_player("firefox", &PLAYERS).unwrap_err();
+
+        assert_eq!(
+            err.matches,
+            vec![
+                "org.mpris.MediaPlayer2.firefox.instance_1_84",
+                "org.mpris.MediaPlayer2.firefox.instance_1_97"
+            ]
+        );
+        assert!(err.to_string().starts_with("Player firefox is ambiguous"));
+    }
+
+    #[test]
+    fn test_resolve_alias() {
+        let aliases = HashMap::from([(String::from("music"), String::from("spotify"))]);
+
+        assert_eq!(resolve_alias("music", &aliases), "spotify");
+        assert_eq!(resolve_alias("mpv", &aliases), "mpv");
+    }
+}
@@ -1,5 +1,11 @@
+mod matching;
 mod mpris_wrapper;
+mod open;
+mod policy;
 mod preferred;
 
+pub use matching::*;
 pub use mpris_wrapper::*;
+pub use open::*;
+pub use policy::*;
 pub use preferred::*;
@@ -2,7 +2,7 @@ use super::super::get_player_by_bus_name;
 use super::PlayerState;
 use crate::err::*;
 use anyhow::Result as AnyResult;
-use std::{process, thread};
+use std::thread;
 use tokio::sync::mpsc;
 
 pub fn spawn_mpris_listener(
@@ -28,17 +28,21 @@ pub fn spawn_mpris_listener(
 
         ready_tx.send(Ok(())).or_exit("Failed to send bus name");
 
-        let events = player.events().unwrap_or_else(|e| {
-            eprintln!("Error: {}", e);
-            process::exit(1);
-        });
+        // dropping the sender lets the receiver know the player is gone
+        let events = match player.events() {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("Failed to listen to player events: {}", e);
+                return;
+            }
+        };
 
         let mut player_state = PlayerState::new(&player);
 
         // send initial player state
-        sender
-            .blocking_send(player_state.clone())
-            .or_exit("Failed to send initial state");
+        if sender.blocking_send(player_state.clone()).is_err() {
+            return;
+        }
 
         for event in events {
             match event {
@@ -61,5 +65,7 @@ pub fn spawn_mpris_listener(
         }
     });
 
-    ready_rx.recv().unwrap()
+    ready_rx
+        .recv()
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Listener thread died")))
 }
@@ -1,9 +1,11 @@
 mod player_action;
 mod controller;
 mod listener;
+mod players_listener;
 mod state;
 mod wrapper;
 
 pub use state::*;
 pub use wrapper::*;
 pub use player_action::*;
+pub use players_listener::*;
@@ -1,6 +1,11 @@
+use crate::player::{open_uri, player_can_open};
+use mpris::{DBusError, LoopStatus, Player};
 use std::time::Duration;
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum PlayerAction {
+    Play,
+    Pause,
     PlayPause,
     Next,
     Previous,
@@ -8,4 +13,47 @@ pub enum PlayerAction {
     Seek(Duration),
     Raise,
     Shuffle(bool),
+    Loop(LoopStatus),
+    /// Play a URI, see `to_uri` to open a path
+    OpenUri(String),
+}
+
+impl PlayerAction {
+    /// Run the action on the player
+    pub fn apply(&self, player: &Player) -> Result<(), DBusError> {
+        match self {
+            PlayerAction::Play => player.play(),
+            PlayerAction::Pause => player.pause(),
+            PlayerAction::PlayPause => player.play_pause(),
+            PlayerAction::Next => player.next(),
+            PlayerAction::Previous => player.previous(),
+            PlayerAction::Volume(volume) => player.set_volume(*volume),
+            PlayerAction::Seek(position) => {
+                if !player.can_seek()? {
+                    return Err(DBusError::Miscellaneous(String::from(
+                        "the player can't seek",
+                    )));
+                }
+
+                match player.get_metadata()?.track_id() {
+                    Some(track_id) => player.set_position(track_id, position),
+                    None => Err(DBusError::Miscellaneous(String::from(
+                        "the track has no id",
+                    ))),
+                }
+            }
+            PlayerAction::Raise => player.raise(),
+            PlayerAction::Shuffle(shuffle) => player.set_shuffle(*shuffle),
+            PlayerAction::Loop(status) => player.set_loop_status(*status),
+            PlayerAction::OpenUri(uri) => {
+                if !player_can_open(player, uri)? {
+                    return Err(DBusError::Miscellaneous(format!(
+                        "the player can't open {uri}"
+                    )));
+                }
+
+                open_uri(player, uri)
+            }
+        }
+    }
 }
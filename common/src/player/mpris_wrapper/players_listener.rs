@@ -0,0 +1,109 @@
+use super::super::get_player_by_bus_name;
+use super::PlayerState;
+use anyhow::Result as AnyResult;
+use mpris::PlayerFinder;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// there's no "player appeared" event, so we have to look for new players
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum PlayersEvent {
+    Changed(PlayerState),
+    Vanished(String),
+}
+
+/// Listen to every player, including the ones that show up later.
+///
+/// Each player gets its own thread, its state is sent as
+/// `PlayersEvent::Changed` and `PlayersEvent::Vanished` is sent once it's gone.
+pub fn spawn_players_listener(sender: mpsc::Sender<PlayersEvent>) -> AnyResult<()> {
+    // make sure we can talk to D-Bus before spawning anything
+    PlayerFinder::new()?;
+
+    thread::spawn(move || {
+        let known_players = Arc::new(Mutex::new(HashSet::new()));
+
+        while !sender.is_closed() {
+            let player_names = match list_player_names() {
+                Ok(names) => names,
+                Err(err) => {
+                    eprintln!("Failed to list players: {:?}", err);
+                    vec![]
+                }
+            };
+
+            for player_name in player_names {
+                if !known_players.lock().unwrap().insert(player_name.clone()) {
+                    continue;
+                }
+
+                let sender = sender.clone();
+                let known_players = known_players.clone();
+
+                thread::spawn(move || {
+                    listen_to_player(&player_name, &sender);
+                    known_players.lock().unwrap().remove(&player_name);
+                    let _ = sender.blocking_send(PlayersEvent::Vanished(player_name));
+                });
+            }
+
+            thread::sleep(SCAN_INTERVAL);
+        }
+    });
+
+    Ok(())
+}
+
+fn list_player_names() -> AnyResult<Vec<String>> {
+    let players = PlayerFinder::new()?.find_all()?;
+
+    Ok(players
+        .iter()
+        .map(|player| player.bus_name().to_string())
+        .collect())
+}
+
+fn listen_to_player(player_name: &str, sender: &mpsc::Sender<PlayersEvent>) {
+    let player = match get_player_by_bus_name(player_name) {
+        Ok(Some(player)) => player,
+        _ => return,
+    };
+
+    let events = match player.events() {
+        Ok(events) => events,
+        Err(_) => return,
+    };
+
+    let mut player_state = PlayerState::new(&player);
+
+    if sender
+        .blocking_send(PlayersEvent::Changed(player_state.clone()))
+        .is_err()
+    {
+        return;
+    }
+
+    for event in events {
+        let event = match event {
+            Ok(event) => event,
+            Err(_) => return,
+        };
+
+        player_state = match player_state.handle_event(event) {
+            Some(state) => state,
+            None => return,
+        };
+
+        if sender
+            .blocking_send(PlayersEvent::Changed(player_state.clone()))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
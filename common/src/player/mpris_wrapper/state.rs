@@ -1,4 +1,6 @@
+use super::super::{matches_pattern, resolve_alias};
 use mpris::Event;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct PlayerState {
@@ -21,11 +23,17 @@ impl PlayerState {
                 .get_playback_status()
                 .unwrap_or(mpris::PlaybackStatus::Stopped),
             loop_status: player.get_loop_status().unwrap_or(mpris::LoopStatus::None),
-            shuffle: false,
+            shuffle: player.get_shuffle().unwrap_or(false),
             volume: player.get_volume().unwrap_or(1.0),
         }
     }
 
+    /// Check if `pattern`, which may be an alias, refers to this player (see
+    /// `matches_pattern`)
+    pub fn matches(&self, pattern: &str, aliases: &HashMap<String, String>) -> bool {
+        matches_pattern(&self.name, &self.identity, resolve_alias(pattern, aliases))
+    }
+
     pub fn handle_event(mut self, event: mpris::Event) -> Option<Self> {
         match event {
             // the easy ones :)
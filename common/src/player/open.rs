@@ -0,0 +1,189 @@
+use super::find_all_players;
+use anyhow::{Context, Result as AnyResult};
+use dbus::blocking::Connection;
+use mpris::{DBusError, Player};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+// extension, mime type
+const MIME_TYPES: [(&str, &str); 16] = [
+    ("aac", "audio/aac"),
+    ("flac", "audio/flac"),
+    ("m4a", "audio/mp4"),
+    ("mp3", "audio/mpeg"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("opus", "audio/ogg"),
+    ("wav", "audio/x-wav"),
+    ("wma", "audio/x-ms-wma"),
+    ("avi", "video/x-msvideo"),
+    ("m4v", "video/mp4"),
+    ("mkv", "video/x-matroska"),
+    ("mov", "video/quicktime"),
+    ("mp4", "video/mp4"),
+    ("ogv", "video/ogg"),
+    ("webm", "video/webm"),
+];
+
+// `https` for `https://example.com`, but nothing for `C:` or a plain path
+fn uri_scheme(uri: &str) -> Option<&str> {
+    let (scheme, _) = uri.split_once(':')?;
+
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+
+    valid.then_some(scheme)
+}
+
+/// The `file://` URI of an absolute path, with each part of it percent
+/// encoded
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path
+        .to_string_lossy()
+        .split('/')
+        .map(|part| urlencoding::encode(part).into_owned())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    format!("file://{}", path)
+}
+
+/// A URI is kept as it is, while a path, relative or not, becomes a
+/// `file://` URI. The path must exist.
+pub fn to_uri(arg: &str) -> AnyResult<String> {
+    // a file called "a:b" is still a file
+    if uri_scheme(arg).is_some() && !Path::new(arg).exists() {
+        return Ok(arg.to_string());
+    }
+
+    let path = fs::canonicalize(arg).with_context(|| format!("couldn't find {arg}"))?;
+    Ok(path_to_uri(&path))
+}
+
+/// The mime type of the file the URI points to, from its extension
+pub fn guess_mime_type(uri: &str) -> Option<&'static str> {
+    let name = uri.split(['?', '#']).next()?.rsplit('/').next()?;
+    let (_, extension) = name.rsplit_once('.')?;
+    let extension = extension.to_lowercase();
+
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime_type)| *mime_type)
+}
+
+/// If a player with these `SupportedUriSchemes` and `SupportedMimeTypes` can
+/// open the URI. The mime type is only checked when it can be guessed, and
+/// `audio/*` in the supported ones matches any audio.
+pub fn can_open(schemes: &[String], mime_types: &[String], uri: &str) -> bool {
+    let supports_scheme = uri_scheme(uri).is_some_and(|scheme| {
+        schemes
+            .iter()
+            .any(|supported| supported.eq_ignore_ascii_case(scheme))
+    });
+    if !supports_scheme {
+        return false;
+    }
+
+    match guess_mime_type(uri) {
+        Some(mime_type) if !mime_types.is_empty() => {
+            mime_types
+                .iter()
+                .any(|supported| match supported.strip_suffix("/*") {
+                    Some(kind) => mime_type.split('/').next() == Some(kind),
+                    None => supported == mime_type,
+                })
+        }
+        _ => true,
+    }
+}
+
+pub fn player_can_open(player: &Player, uri: &str) -> Result<bool, DBusError> {
+    Ok(can_open(
+        &player.get_supported_uri_schemes()?,
+        &player.get_supported_mime_types()?,
+        uri,
+    ))
+}
+
+/// The first running player that can open the URI. Players that don't say
+/// what they support (many browsers) are skipped.
+pub fn find_player_for_uri(uri: &str) -> AnyResult<Option<Player>> {
+    for player in find_all_players()? {
+        if player_can_open(&player, uri).unwrap_or(false) {
+            return Ok(Some(player));
+        }
+    }
+    Ok(None)
+}
+
+/// Call `OpenUri` on the player, which mpris doesn't expose
+pub fn open_uri(player: &Player, uri: &str) -> Result<(), DBusError> {
+    let conn = Connection::new_session()?;
+    let proxy = conn.with_proxy(player.bus_name(), MPRIS_PATH, DBUS_TIMEOUT);
+
+    let () = proxy.method_call(PLAYER_INTERFACE, "OpenUri", (uri,))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_to_uri() {
+        assert_eq!(
+            to_uri("https://example.com/a.mp3").unwrap(),
+            "https://example.com/a.mp3"
+        );
+
+        let uri = to_uri("Cargo.toml").unwrap();
+        assert!(uri.starts_with("file:///"));
+        assert!(uri.ends_with("/common/Cargo.toml"));
+
+        assert!(to_uri("no/such/file.mp3").is_err());
+    }
+
+    #[test]
+    fn test_path_to_uri() {
+        assert_eq!(
+            path_to_uri(Path::new("/music/a b#1.mp3")),
+            "file:///music/a%20b%231.mp3"
+        );
+    }
+
+    #[test]
+    fn test_guess_mime_type() {
+        assert_eq!(guess_mime_type("file:///music/a.MP3"), Some("audio/mpeg"));
+        assert_eq!(
+            guess_mime_type("https://example.com/v.webm?t=10"),
+            Some("video/webm")
+        );
+        assert_eq!(guess_mime_type("https://example.com/watch"), None);
+    }
+
+    #[test]
+    fn test_can_open() {
+        let schemes = strings(&["file", "http", "https"]);
+        let mime_types = strings(&["audio/mpeg", "video/*"]);
+
+        assert!(can_open(&schemes, &mime_types, "file:///a.mp3"));
+        assert!(can_open(&schemes, &mime_types, "file:///a.mkv"));
+        assert!(can_open(&schemes, &mime_types, "https://example.com/live"));
+        assert!(!can_open(&schemes, &mime_types, "file:///a.flac"));
+        assert!(!can_open(&schemes, &mime_types, "spotify:track:123"));
+        assert!(can_open(&schemes, &[], "file:///a.flac"));
+    }
+}
@@ -0,0 +1,190 @@
+use super::{Policy, PolicyAction};
+use crate::config::DuckingConfig;
+use crate::player::{PlayerAction, PlayerState, PlayersEvent};
+use mpris::PlaybackStatus;
+use std::collections::{HashMap, HashSet};
+
+/// Lower the volume of the other players while a "voice" player is playing
+pub struct Ducking {
+    config: DuckingConfig,
+    aliases: HashMap<String, String>,
+    players: HashMap<String, PlayerState>,
+    // voice players that are currently playing
+    active_voices: HashSet<String>,
+    // the volume each ducked player had before being ducked
+    saved_volumes: HashMap<String, f64>,
+}
+
+impl Ducking {
+    pub fn new(config: DuckingConfig, aliases: HashMap<String, String>) -> Self {
+        Ducking {
+            config,
+            aliases,
+            players: HashMap::new(),
+            active_voices: HashSet::new(),
+            saved_volumes: HashMap::new(),
+        }
+    }
+
+    fn is_voice(&self, state: &PlayerState) -> bool {
+        self.config
+            .voice
+            .iter()
+            .any(|p| state.matches(p, &self.aliases))
+    }
+
+    fn duck(&mut self, state: &PlayerState) -> Option<PolicyAction> {
+        if self.is_voice(state) || self.saved_volumes.contains_key(&state.name) {
+            return None;
+        }
+
+        self.saved_volumes.insert(state.name.clone(), state.volume);
+
+        let ratio = self.config.ratio.clamp(0.0, 1.0);
+        Some((
+            state.name.clone(),
+            PlayerAction::Volume(state.volume * ratio),
+        ))
+    }
+
+    fn duck_all(&mut self) -> Vec<PolicyAction> {
+        let players = self.players.values().cloned().collect::<Vec<_>>();
+
+        players.iter().filter_map(|p| self.duck(p)).collect()
+    }
+
+    fn restore_all(&mut self) -> Vec<PolicyAction> {
+        self.saved_volumes
+            .drain()
+            .filter(|(name, _)| self.players.contains_key(name))
+            .map(|(name, volume)| (name, PlayerAction::Volume(volume)))
+            .collect()
+    }
+
+    fn voice_stopped(&mut self, name: &str) -> Vec<PolicyAction> {
+        if self.active_voices.remove(name) && self.active_voices.is_empty() {
+            self.restore_all()
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl Policy for Ducking {
+    fn handle_event(&mut self, event: &PlayersEvent) -> Vec<PolicyAction> {
+        match event {
+            PlayersEvent::Changed(state) => {
+                self.players.insert(state.name.clone(), state.clone());
+
+                if !self.is_voice(state) {
+                    // a player that showed up while ducking
+                    if self.active_voices.is_empty() {
+                        return vec![];
+                    }
+                    return self.duck(state).into_iter().collect();
+                }
+
+                if state.playback_status == PlaybackStatus::Playing {
+                    let was_ducking = !self.active_voices.is_empty();
+                    self.active_voices.insert(state.name.clone());
+
+                    if was_ducking {
+                        vec![]
+                    } else {
+                        self.duck_all()
+                    }
+                } else {
+                    self.voice_stopped(&state.name)
+                }
+            }
+            PlayersEvent::Vanished(name) => {
+                self.players.remove(name);
+                self.saved_volumes.remove(name);
+                self.voice_stopped(name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(name: &str, status: PlaybackStatus, volume: f64) -> PlayersEvent {
+        PlayersEvent::Changed(PlayerState {
+            identity: name.to_string(),
+            name: format!("org.mpris.MediaPlayer2.{name}"),
+            metadata: mpris::Metadata::default(),
+            playback_status: status,
+            loop_status: mpris::LoopStatus::None,
+            shuffle: false,
+            volume,
+        })
+    }
+
+    fn volume(name: &str, volume: f64) -> PolicyAction {
+        (
+            format!("org.mpris.MediaPlayer2.{name}"),
+            PlayerAction::Volume(volume),
+        )
+    }
+
+    fn new_policy() -> Ducking {
+        Ducking::new(
+            DuckingConfig {
+                enabled: true,
+                voice: vec!["zoom".to_string()],
+                ratio: 0.5,
+            },
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_duck_and_restore() {
+        let mut policy = new_policy();
+
+        policy.handle_event(&state("spotify", PlaybackStatus::Playing, 0.8));
+        policy.handle_event(&state("zoom", PlaybackStatus::Paused, 1.0));
+
+        assert_eq!(
+            policy.handle_event(&state("zoom", PlaybackStatus::Playing, 1.0)),
+            vec![volume("spotify", 0.4)]
+        );
+        // the event caused by ducking must not change the saved volume
+        assert!(policy
+            .handle_event(&state("spotify", PlaybackStatus::Playing, 0.4))
+            .is_empty());
+        assert_eq!(
+            policy.handle_event(&state("zoom", PlaybackStatus::Paused, 1.0)),
+            vec![volume("spotify", 0.8)]
+        );
+    }
+
+    #[test]
+    fn test_restore_on_vanish() {
+        let mut policy = new_policy();
+
+        policy.handle_event(&state("spotify", PlaybackStatus::Playing, 0.6));
+        policy.handle_event(&state("zoom", PlaybackStatus::Playing, 1.0));
+
+        assert_eq!(
+            policy.handle_event(&PlayersEvent::Vanished(
+                "org.mpris.MediaPlayer2.zoom".to_string()
+            )),
+            vec![volume("spotify", 0.6)]
+        );
+    }
+
+    #[test]
+    fn test_duck_new_players() {
+        let mut policy = new_policy();
+
+        policy.handle_event(&state("zoom", PlaybackStatus::Playing, 1.0));
+
+        assert_eq!(
+            policy.handle_event(&state("spotify", PlaybackStatus::Playing, 1.0)),
+            vec![volume("spotify", 0.5)]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use super::{Policy, PolicyAction};
+use crate::config::ExclusiveConfig;
+use crate::player::{PlayerAction, PlayerState, PlayersEvent};
+use mpris::PlaybackStatus;
+use std::collections::HashMap;
+
+/// Pause the other players when one starts playing
+pub struct ExclusivePlayback {
+    config: ExclusiveConfig,
+    aliases: HashMap<String, String>,
+    players: HashMap<String, PlayerState>,
+    // the players paused by each "interrupting" player, so they can be resumed
+    paused_by: HashMap<String, Vec<String>>,
+}
+
+impl ExclusivePlayback {
+    pub fn new(config: ExclusiveConfig, aliases: HashMap<String, String>) -> Self {
+        ExclusivePlayback {
+            config,
+            aliases,
+            players: HashMap::new(),
+            paused_by: HashMap::new(),
+        }
+    }
+
+    fn is_exempt(&self, state: &PlayerState) -> bool {
+        self.config
+            .except
+            .iter()
+            .any(|p| state.matches(p, &self.aliases))
+    }
+
+    fn pause_others(&mut self, state: &PlayerState) -> Vec<PolicyAction> {
+        let to_pause = self
+            .players
+            .values()
+            .filter(|p| p.name != state.name)
+            .filter(|p| p.playback_status == PlaybackStatus::Playing)
+            .filter(|p| !self.is_exempt(p))
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+
+        for name in &to_pause {
+            if let Some(player) = self.players.get_mut(name) {
+                player.playback_status = PlaybackStatus::Paused;
+            }
+        }
+
+        self.paused_by
+            .entry(state.name.clone())
+            .or_default()
+            .extend(to_pause.iter().cloned());
+
+        to_pause
+            .into_iter()
+            .map(|name| (name, PlayerAction::Pause))
+            .collect()
+    }
+
+    fn resume_paused_by(&mut self, name: &str) -> Vec<PolicyAction> {
+        if !self.config.resume {
+            self.paused_by.remove(name);
+            return vec![];
+        }
+
+        self.paused_by
+            .remove(name)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|name| {
+                self.players
+                    .get(name)
+                    .is_some_and(|p| p.playback_status == PlaybackStatus::Paused)
+            })
+            .map(|name| (name, PlayerAction::Play))
+            .collect()
+    }
+
+    // the player was resumed by someone else, no need to resume it later
+    fn forget_paused(&mut self, name: &str) {
+        for paused in self.paused_by.values_mut() {
+            paused.retain(|n| n != name);
+        }
+    }
+}
+
+impl Policy for ExclusivePlayback {
+    fn handle_event(&mut self, event: &PlayersEvent) -> Vec<PolicyAction> {
+        match event {
+            PlayersEvent::Changed(state) => {
+                let prev_status = self
+                    .players
+                    .insert(state.name.clone(), state.clone())
+                    .map(|p| p.playback_status);
+
+                let was_playing = prev_status == Some(PlaybackStatus::Playing);
+                let is_playing = state.playback_status == PlaybackStatus::Playing;
+
+                if is_playing && !was_playing {
+                    self.forget_paused(&state.name);
+                    if self.is_exempt(state) {
+                        return vec![];
+                    }
+                    self.pause_others(state)
+                } else if was_playing && !is_playing {
+                    self.resume_paused_by(&state.name)
+                } else {
+                    vec![]
+                }
+            }
+            PlayersEvent::Vanished(name) => {
+                self.players.remove(name);
+                self.forget_paused(name);
+                self.resume_paused_by(name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(name: &str, status: PlaybackStatus) -> PlayersEvent {
+        PlayersEvent::Changed(PlayerState {
+            identity: name.to_string(),
+            name: format!("org.mpris.MediaPlayer2.{name}"),
+            metadata: mpris::Metadata::default(),
+            playback_status: status,
+            loop_status: mpris::LoopStatus::None,
+            shuffle: false,
+            volume: 1.0,
+        })
+    }
+
+    fn action(name: &str, action: PlayerAction) -> PolicyAction {
+        (format!("org.mpris.MediaPlayer2.{name}"), action)
+    }
+
+    fn new_policy(except: Vec<&str>, resume: bool) -> ExclusivePlayback {
+        ExclusivePlayback::new(
+            ExclusiveConfig {
+                enabled: true,
+                except: except.into_iter().map(String::from).collect(),
+                resume,
+            },
+            HashMap::from([(String::from("browser"), String::from("firefox.*"))]),
+        )
+    }
+
+    #[test]
+    fn test_pause_others() {
+        let mut policy = new_policy(vec![], false);
+
+        assert!(policy
+            .handle_event(&state("spotify", PlaybackStatus::Playing))
+            .is_empty());
+        assert_eq!(
+            policy.handle_event(&state("firefox", PlaybackStatus::Playing)),
+            vec![action("spotify", PlayerAction::Pause)]
+        );
+        assert!(policy
+            .handle_event(&state("firefox", PlaybackStatus::Paused))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let mut policy = new_policy(vec!["mpv"], false);
+
+        policy.handle_event(&state("mpv", PlaybackStatus::Playing));
+        assert!(policy
+            .handle_event(&state("spotify", PlaybackStatus::Playing))
+            .is_empty());
+        assert!(policy
+            .handle_event(&state("mpv", PlaybackStatus::Paused))
+            .is_empty());
+        assert!(policy
+            .handle_event(&state("mpv", PlaybackStatus::Playing))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_alias_and_glob_exceptions() {
+        let mut policy = new_policy(vec!["browser", "mp?"], false);
+
+        policy.handle_event(&state("firefox.instance_1_84", PlaybackStatus::Playing));
+        policy.handle_event(&state("mpv", PlaybackStatus::Playing));
+        assert!(policy
+            .handle_event(&state("spotify", PlaybackStatus::Playing))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_resume_on_stop() {
+        let mut policy = new_policy(vec![], true);
+
+        policy.handle_event(&state("spotify", PlaybackStatus::Playing));
+        policy.handle_event(&state("firefox", PlaybackStatus::Playing));
+        policy.handle_event(&state("spotify", PlaybackStatus::Paused));
+
+        assert_eq!(
+            policy.handle_event(&state("firefox", PlaybackStatus::Stopped)),
+            vec![action("spotify", PlayerAction::Play)]
+        );
+    }
+
+    #[test]
+    fn test_resume_on_vanish() {
+        let mut policy = new_policy(vec![], true);
+
+        policy.handle_event(&state("spotify", PlaybackStatus::Playing));
+        policy.handle_event(&state("firefox", PlaybackStatus::Playing));
+
+        assert_eq!(
+            policy.handle_event(&PlayersEvent::Vanished(
+                "org.mpris.MediaPlayer2.firefox".to_string()
+            )),
+            vec![action("spotify", PlayerAction::Play)]
+        );
+    }
+
+    #[test]
+    fn test_no_resume_if_resumed_manually() {
+        let mut policy = new_policy(vec![], true);
+
+        policy.handle_event(&state("spotify", PlaybackStatus::Playing));
+        policy.handle_event(&state("firefox", PlaybackStatus::Playing));
+        policy.handle_event(&state("spotify", PlaybackStatus::Paused));
+        // the user resumed spotify, so firefox gets paused
+        assert_eq!(
+            policy.handle_event(&state("spotify", PlaybackStatus::Playing)),
+            vec![action("firefox", PlayerAction::Pause)]
+        );
+        policy.handle_event(&state("firefox", PlaybackStatus::Paused));
+
+        // spotify pausing should resume firefox, as it was paused by it
+        assert_eq!(
+            policy.handle_event(&state("spotify", PlaybackStatus::Paused)),
+            vec![action("firefox", PlayerAction::Play)]
+        );
+        policy.handle_event(&state("firefox", PlaybackStatus::Playing));
+
+        // but firefox stopping must not resume spotify, the user took it back
+        // from the policy by resuming it
+        assert!(policy
+            .handle_event(&state("firefox", PlaybackStatus::Stopped))
+            .is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use super::{spawn_players_listener, MprisWrapper, PlayerAction, PlayersEvent};
+use crate::config::Config;
+use anyhow::Result as AnyResult;
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use tokio::sync::mpsc as tokio_mpsc;
+
+mod ducking;
+mod exclusive;
+mod notifications;
+pub use ducking::*;
+pub use exclusive::*;
+pub use notifications::*;
+
+/// An action to send to the player with the given bus name
+pub type PolicyAction = (String, PlayerAction);
+
+/// Something that reacts to the state of every player by controlling them
+pub trait Policy: Send {
+    fn handle_event(&mut self, event: &PlayersEvent) -> Vec<PolicyAction>;
+}
+
+/// Start the policies enabled in the config, if any.
+///
+/// Returns the handle of the thread running them, so it can be joined by
+/// whoever has nothing else to do.
+pub fn spawn_policies(config: &Config) -> AnyResult<Option<JoinHandle<()>>> {
+    let mut policies: Vec<Box<dyn Policy>> = vec![];
+
+    if config.exclusive.enabled {
+        policies.push(Box::new(ExclusivePlayback::new(
+            config.exclusive.clone(),
+            config.aliases.clone(),
+        )));
+    }
+
+    if config.ducking.enabled {
+        policies.push(Box::new(Ducking::new(
+            config.ducking.clone(),
+            config.aliases.clone(),
+        )));
+    }
+
+    if config.notifications.enabled {
+        policies.push(Box::new(TrackNotifications::new()?));
+    }
+
+    if policies.is_empty() {
+        return Ok(None);
+    }
+
+    let (event_tx, mut event_rx) = tokio_mpsc::channel(16);
+    spawn_players_listener(event_tx)?;
+
+    Ok(Some(thread::spawn(move || {
+        let mut controllers = HashMap::new();
+
+        while let Some(event) = event_rx.blocking_recv() {
+            if let PlayersEvent::Vanished(name) = &event {
+                controllers.remove(name);
+            }
+
+            for policy in policies.iter_mut() {
+                for (player_name, action) in policy.handle_event(&event) {
+                    send_action(&mut controllers, player_name, action);
+                }
+            }
+        }
+    })))
+}
+
+fn send_action(
+    controllers: &mut HashMap<String, mpsc::Sender<PlayerAction>>,
+    player_name: String,
+    action: PlayerAction,
+) {
+    if !controllers.contains_key(&player_name) {
+        let (action_tx, action_rx) = mpsc::channel();
+        let wrapper = MprisWrapper::new(player_name.clone());
+
+        if let Err(err) = wrapper.start_controller(action_rx) {
+            eprintln!("Failed to control player {}: {:?}", player_name, err);
+            return;
+        }
+        controllers.insert(player_name.clone(), action_tx);
+    }
+
+    if let Some(controller) = controllers.get(&player_name) {
+        if controller.send(action).is_err() {
+            controllers.remove(&player_name);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use super::{send_action, Policy, PolicyAction};
+use crate::art::{download_art, get_cached_art_path, resolve_art_url, ArtSource};
+use crate::player::{PlayerAction, PlayerState, PlayersEvent};
+use anyhow::Result as AnyResult;
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+use mpris::PlaybackStatus;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const NOTIFICATIONS_BUS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+const DBUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+// action key, label
+const ACTIONS: [(&str, &str); 3] = [
+    ("previous", "Previous"),
+    ("play-pause", "Play/Pause"),
+    ("next", "Next"),
+];
+
+/// Show a desktop notification when the track changes
+pub struct TrackNotifications {
+    conn: Connection,
+    // the id of the notification being shown and the player it's about
+    current: Arc<Mutex<Option<(u32, String)>>>,
+    last_tracks: HashMap<String, String>,
+}
+
+impl TrackNotifications {
+    pub fn new() -> AnyResult<Self> {
+        let current = Arc::new(Mutex::new(None));
+
+        spawn_action_listener(current.clone())?;
+
+        Ok(TrackNotifications {
+            conn: Connection::new_session()?,
+            current,
+            last_tracks: HashMap::new(),
+        })
+    }
+
+    fn notify(&self, state: &PlayerState) -> AnyResult<()> {
+        let metadata = &state.metadata;
+
+        let mut body = vec![];
+        if let Some(artists) = metadata.artists().filter(|a| !a.is_empty()) {
+            body.push(artists.join(", "));
+        }
+        if let Some(album) = metadata.album_name().filter(|a| !a.is_empty()) {
+            body.push(album.to_string());
+        }
+
+        let notification = Notification {
+            title: metadata.title().unwrap_or("Unknown title").to_string(),
+            body: body.join("\n"),
+        };
+
+        // only art that is already on disk, a download would hold up the
+        // other policies
+        let (art_path, art_to_download) = match metadata.art_url().and_then(resolve_art_url) {
+            Some(ArtSource::Local(path)) => (Some(path), None),
+            Some(ArtSource::Remote(url)) => {
+                let path = get_cached_art_path(&url);
+                if path.exists() {
+                    (Some(path), None)
+                } else {
+                    (None, Some(url))
+                }
+            }
+            None => (None, None),
+        };
+
+        let id = {
+            let mut current = self.current.lock().unwrap();
+            let replaces_id = current.as_ref().map(|(id, _)| *id).unwrap_or(0);
+
+            let id = send_notification(&self.conn, replaces_id, &notification, art_path)?;
+            current.replace((id, state.name.clone()));
+            id
+        };
+
+        if let Some(url) = art_to_download {
+            spawn_art_download(self.current.clone(), id, notification, url);
+        }
+
+        Ok(())
+    }
+}
+
+struct Notification {
+    title: String,
+    body: String,
+}
+
+fn send_notification(
+    conn: &Connection,
+    replaces_id: u32,
+    notification: &Notification,
+    art_path: Option<PathBuf>,
+) -> AnyResult<u32> {
+    let mut hints = PropMap::new();
+    if let Some(art_path) = art_path {
+        hints.insert(
+            "image-path".to_string(),
+            Variant(Box::new(art_path.to_string_lossy().to_string()) as Box<dyn RefArg>),
+        );
+    }
+
+    let actions = ACTIONS
+        .iter()
+        .flat_map(|(key, label)| [*key, *label])
+        .collect::<Vec<_>>();
+
+    let proxy = conn.with_proxy(NOTIFICATIONS_BUS_NAME, NOTIFICATIONS_PATH, DBUS_TIMEOUT);
+    let (id,): (u32,) = proxy.method_call(
+        NOTIFICATIONS_BUS_NAME,
+        "Notify",
+        (
+            "rstroller",
+            replaces_id,
+            "",
+            notification.title.as_str(),
+            notification.body.as_str(),
+            actions,
+            hints,
+            -1,
+        ),
+    )?;
+
+    Ok(id)
+}
+
+// download the art away from the policy thread, then add it to the
+// notification if it's still the one being shown
+fn spawn_art_download(
+    current: Arc<Mutex<Option<(u32, String)>>>,
+    id: u32,
+    notification: Notification,
+    url: String,
+) {
+    thread::spawn(move || {
+        let art_path = get_cached_art_path(&url);
+
+        let result = download_art(&url, &art_path).and_then(|_| {
+            let conn = Connection::new_session()?;
+            let current = current.lock().unwrap();
+
+            if current
+                .as_ref()
+                .is_some_and(|(current_id, _)| *current_id == id)
+            {
+                send_notification(&conn, id, &notification, Some(art_path))?;
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            eprintln!("Failed to add the art to the notification: {:?}", err);
+        }
+    });
+}
+
+impl Policy for TrackNotifications {
+    fn handle_event(&mut self, event: &PlayersEvent) -> Vec<PolicyAction> {
+        match event {
+            PlayersEvent::Changed(state) => {
+                let track = track_key(state);
+                let prev_track = self.last_tracks.insert(state.name.clone(), track.clone());
+
+                // the first state of a player is not a track change
+                let changed = prev_track.is_some_and(|prev| prev != track);
+
+                if changed && state.playback_status == PlaybackStatus::Playing {
+                    if let Err(err) = self.notify(state) {
+                        eprintln!("Failed to send notification: {:?}", err);
+                    }
+                }
+            }
+            PlayersEvent::Vanished(name) => {
+                self.last_tracks.remove(name);
+            }
+        }
+
+        vec![]
+    }
+}
+
+fn track_key(state: &PlayerState) -> String {
+    let metadata = &state.metadata;
+
+    match metadata.track_id() {
+        Some(track_id) => track_id.to_string(),
+        None => format!(
+            "{:?} {:?} {:?}",
+            metadata.title(),
+            metadata.artists(),
+            metadata.album_name()
+        ),
+    }
+}
+
+// buttons clicked in the notification are sent back as signals
+fn spawn_action_listener(current: Arc<Mutex<Option<(u32, String)>>>) -> AnyResult<()> {
+    let conn = Connection::new_session()?;
+    let (tx, rx) = mpsc::channel();
+
+    conn.add_match(
+        MatchRule::new_signal(NOTIFICATIONS_BUS_NAME, "ActionInvoked"),
+        move |(id, key): (u32, String), _, _| tx.send((id, key)).is_ok(),
+    )?;
+
+    thread::spawn(move || {
+        let mut controllers = HashMap::new();
+
+        loop {
+            if conn.process(Duration::from_secs(1)).is_err() {
+                eprintln!("Lost connection to the notifications server");
+                return;
+            }
+
+            while let Ok((id, key)) = rx.try_recv() {
+                let player_name = match current.lock().unwrap().as_ref() {
+                    Some((current_id, name)) if *current_id == id => name.clone(),
+                    _ => continue,
+                };
+
+                let action = match key.as_str() {
+                    "previous" => PlayerAction::Previous,
+                    "play-pause" => PlayerAction::PlayPause,
+                    "next" => PlayerAction::Next,
+                    _ => continue,
+                };
+
+                send_action(&mut controllers, player_name, action);
+            }
+        }
+    });
+
+    Ok(())
+}
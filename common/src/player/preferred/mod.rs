@@ -1,8 +1,9 @@
 use anyhow::{Context, Result as AnyResult};
-use itertools::Itertools;
-use mpris::{Player, PlayerFinder};
+use mpris::Player;
 use std::{fs, io, path::Path};
 
+use super::player_finder;
+
 mod watcher;
 pub use watcher::*;
 
@@ -21,16 +22,19 @@ pub fn set_preferred_player_name(name: &str) -> AnyResult<()> {
         .context("couldn't write the preferred player file")
 }
 
+/// The player with exactly this bus name, user input goes through
+/// `find_player` instead
 pub fn get_player_by_bus_name(name: &str) -> AnyResult<Option<Player>> {
-    let finder = PlayerFinder::new()?;
+    let finder = player_finder()?;
 
     Ok(finder
-        .iter_players()?
-        .process_results(|mut i| i.find(|p| p.bus_name() == name))?)
+        .find_all()?
+        .into_iter()
+        .find(|player| player.bus_name() == name))
 }
 
 pub fn get_first_player() -> AnyResult<Option<Player>> {
-    let finder = PlayerFinder::new()?;
+    let finder = player_finder()?;
 
     // QUESTION: which bear is best? well, there's basically two schools of thought
     Ok(finder.find_all()?.into_iter().next())
@@ -1,7 +1,6 @@
-use crate::err::*;
 use anyhow::{Context, Result as AnyResult};
 use notify::{event::AccessKind, event::AccessMode, EventKind, Watcher};
-use std::{fs, path::Path, process};
+use std::{fs, path::Path};
 use tokio::sync::mpsc::Receiver;
 
 use super::PREFERRED_PLAYER_FILE_PATH;
@@ -29,15 +28,16 @@ impl PreferredPlayerListener {
                     if event.kind == EventKind::Access(AccessKind::Close(AccessMode::Write)) {
                         let player_name = fs::read_to_string(PREFERRED_PLAYER_FILE_PATH);
 
-                        tx.blocking_send(
+                        // nobody is listening anymore, nothing to do
+                        let _ = tx.blocking_send(
                             player_name.context("Failed to read preferred player file"),
-                        )
-                        .or_exit("Failed to send message in mpsc");
+                        );
                     }
                 }
-                Err(_) => {
-                    eprintln!("Failed to watch to preferred player file");
-                    process::exit(1);
+                Err(err) => {
+                    let _ = tx.blocking_send(
+                        Err(err).context("Failed to watch the preferred player file"),
+                    );
                 }
             },
         )?);
@@ -1,14 +1,117 @@
-/// Truncate a string and add ellipsis if it's longer than `max_length`
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How many terminal columns a string takes, wide chars (like CJK and most
+/// emojis) take two columns while combining chars take none
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// Split a string into grapheme clusters (what users see as a single char)
+pub fn split_graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+/// Truncate a string and add ellipsis if it's wider than `max_width`
 ///
 /// # Output
 ///
-/// The &str itself converted to a String if it's shorter than `max_length`
-/// or a truncated version with an ellipsis at the end.
+/// The &str itself converted to a String if it fits in `max_width` columns
+/// or a truncated version with an ellipsis at the end. Grapheme clusters are
+/// never split and the ellipsis counts as one column.
 ///
-pub fn truncate_string(s: &str, max_length: usize) -> String {
-    if s.len() <= max_length {
-        s.to_string()
+pub fn truncate_string(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
+    }
+
+    let mut width = 0;
+    let mut truncated = String::new();
+
+    for grapheme in s.graphemes(true) {
+        width += grapheme.width();
+        if width + 1 > max_width {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+
+    format!("{}…", truncated)
+}
+
+/// Pad a string with spaces until it's `width` columns wide
+pub fn pad_string(s: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(padding))
+}
+
+/// Format a duration as `m:ss`, or `h:mm:ss` if it's longer than an hour
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
     } else {
-        format!("{}…", &s.chars().take(max_length - 1).collect::<String>())
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👨‍👩‍👧"), 2);
+    }
+
+    #[test]
+    fn test_truncate_ascii() {
+        assert_eq!(truncate_string("hello", 5), "hello");
+        assert_eq!(truncate_string("hello world", 6), "hello…");
+    }
+
+    #[test]
+    fn test_truncate_japanese() {
+        assert_eq!(truncate_string("日本語", 6), "日本語");
+        assert_eq!(truncate_string("夜に駆ける", 5), "夜に…");
+        // a wide char doesn't fit before the ellipsis, so it's one column short
+        assert_eq!(truncate_string("夜に駆ける", 6), "夜に…");
+    }
+
+    #[test]
+    fn test_truncate_combining_accents() {
+        let s = "cafe\u{301} cafe\u{301}";
+        assert_eq!(truncate_string(s, 9), s);
+        assert_eq!(truncate_string(s, 5), "cafe\u{301}…");
+        assert_eq!(truncate_string(s, 4), "caf…");
+    }
+
+    #[test]
+    fn test_truncate_emoji_zwj() {
+        let family = "👨‍👩‍👧";
+        let two_families = format!("{family}{family}");
+        assert_eq!(truncate_string(&two_families, 4), two_families);
+        assert_eq!(truncate_string(&two_families, 3), format!("{family}…"));
+        assert_eq!(truncate_string(&format!("a{family}b"), 3), "a…");
+    }
+
+    #[test]
+    fn test_pad_string() {
+        assert_eq!(pad_string("ab", 4), "ab  ");
+        assert_eq!(pad_string("日本", 5), "日本 ");
+        assert_eq!(pad_string("toolong", 3), "toolong");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0:00");
+        assert_eq!(format_duration(Duration::from_millis(83_900)), "1:23");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
     }
 }
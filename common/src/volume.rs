@@ -0,0 +1,242 @@
+use crate::config::VolumeConfig;
+use anyhow::{Context, Result as AnyResult};
+use std::collections::HashMap;
+use std::{fs, io, path::Path};
+
+// the volume of each muted player, to restore it when unmuting
+const MUTED_VOLUMES_FILE_PATH: &str = "/dev/shm/rstroller-muted";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeChange {
+    Set(f64),
+    /// Added to the current volume, negative to lower it
+    Offset(f64),
+    /// The current volume is multiplied by this, from a change in decibels
+    Gain(f64),
+    /// Raise by the configured step
+    Up,
+    /// Lower by the configured step
+    Down,
+    Mute,
+    Unmute,
+    ToggleMute,
+}
+
+// a plain float (0.5) or a percentage (50%)
+fn parse_level(value: &str) -> Result<f64, String> {
+    let level = match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|percent| percent / 100.0),
+        None => value.parse::<f64>(),
+    }
+    .map_err(|e| format!("{value} is not a number: {e}"))?;
+
+    if !level.is_finite() {
+        return Err(format!("{value} is not a number"));
+    }
+    Ok(level)
+}
+
+/// Parse a volume like `0.5`, `40%`, `5%+`, `0.1-`, `-3dB`, `up`, `down`,
+/// `mute`, `unmute` or `toggle-mute`
+pub fn parse_volume_change(arg: &str) -> Result<VolumeChange, String> {
+    let lower_arg = arg.to_lowercase();
+
+    match lower_arg.as_str() {
+        "up" => return Ok(VolumeChange::Up),
+        "down" => return Ok(VolumeChange::Down),
+        "mute" => return Ok(VolumeChange::Mute),
+        "unmute" => return Ok(VolumeChange::Unmute),
+        "toggle-mute" => return Ok(VolumeChange::ToggleMute),
+        _ => {}
+    }
+
+    if let Some(db) = lower_arg.strip_suffix("db") {
+        let db = db
+            .parse::<f64>()
+            .ok()
+            .filter(|db| db.is_finite())
+            .ok_or_else(|| format!("Invalid decibels {arg}, please use eg: -3dB or +3dB"))?;
+
+        return Ok(VolumeChange::Gain(10f64.powf(db / 20.0)));
+    }
+
+    if let Some(offset) = arg.strip_suffix('+') {
+        return parse_level(offset).map(VolumeChange::Offset);
+    }
+    if let Some(offset) = arg.strip_suffix('-') {
+        return parse_level(offset).map(|offset| VolumeChange::Offset(-offset));
+    }
+
+    parse_level(arg).map(VolumeChange::Set)
+}
+
+/// The new volume and, if the player is muted, the volume to restore when
+/// unmuting.
+///
+/// `muted` is the volume saved when the player was muted, it's forgotten if
+/// the player was unmuted by something else in the meantime.
+pub fn apply_volume_change(
+    change: VolumeChange,
+    current: f64,
+    muted: Option<f64>,
+    config: &VolumeConfig,
+) -> (f64, Option<f64>) {
+    let muted = muted.filter(|_| current == 0.0);
+
+    match change {
+        VolumeChange::Set(volume) => (config.clamp(volume), None),
+        VolumeChange::Offset(offset) => (config.clamp(current + offset), None),
+        VolumeChange::Gain(gain) => (config.clamp(current * gain), None),
+        VolumeChange::Up => (config.clamp(current + config.step), None),
+        VolumeChange::Down => (config.clamp(current - config.step), None),
+        VolumeChange::Mute => match muted {
+            Some(_) => (current, muted),
+            None => (0.0, Some(current)),
+        },
+        VolumeChange::Unmute => match muted {
+            Some(volume) => (config.clamp(volume), None),
+            None => (current, None),
+        },
+        VolumeChange::ToggleMute => match muted {
+            Some(volume) => (config.clamp(volume), None),
+            None => (0.0, Some(current)),
+        },
+    }
+}
+
+// one "bus_name volume" per line
+fn parse_muted_volumes(content: &str) -> HashMap<String, f64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (name, volume) = line.split_once(' ')?;
+            Some((name.to_string(), volume.parse().ok()?))
+        })
+        .collect()
+}
+
+fn format_muted_volumes(volumes: &HashMap<String, f64>) -> String {
+    let mut lines = volumes
+        .iter()
+        .map(|(name, volume)| format!("{} {}\n", name, volume))
+        .collect::<Vec<_>>();
+    lines.sort();
+    lines.concat()
+}
+
+fn read_muted_volumes() -> AnyResult<HashMap<String, f64>> {
+    match fs::read_to_string(Path::new(MUTED_VOLUMES_FILE_PATH)) {
+        Ok(content) => Ok(parse_muted_volumes(&content)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(err) => Err(err).context("couldn't read the muted volumes"),
+    }
+}
+
+/// The volume the player had before being muted
+pub fn get_muted_volume(player_name: &str) -> AnyResult<Option<f64>> {
+    Ok(read_muted_volumes()?.get(player_name).copied())
+}
+
+/// Save the volume to restore when unmuting the player, or forget it
+pub fn set_muted_volume(player_name: &str, volume: Option<f64>) -> AnyResult<()> {
+    let mut volumes = read_muted_volumes()?;
+
+    let changed = match volume {
+        Some(volume) => volumes.insert(player_name.to_string(), volume) != Some(volume),
+        None => volumes.remove(player_name).is_some(),
+    };
+
+    if !changed {
+        return Ok(());
+    }
+
+    fs::write(
+        Path::new(MUTED_VOLUMES_FILE_PATH),
+        format_muted_volumes(&volumes),
+    )
+    .context("couldn't write the muted volumes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_volume_change() {
+        assert_eq!(parse_volume_change("40%"), Ok(VolumeChange::Set(0.4)));
+        assert_eq!(parse_volume_change("0.5"), Ok(VolumeChange::Set(0.5)));
+        assert_eq!(parse_volume_change("5%+"), Ok(VolumeChange::Offset(0.05)));
+        assert_eq!(parse_volume_change("0.1-"), Ok(VolumeChange::Offset(-0.1)));
+        assert_eq!(parse_volume_change("Mute"), Ok(VolumeChange::Mute));
+
+        match parse_volume_change("-6dB") {
+            Ok(VolumeChange::Gain(gain)) => assert!((gain - 0.501).abs() < 0.001),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(parse_volume_change("loud").is_err());
+        assert!(parse_volume_change("nan").is_err());
+        assert!(parse_volume_change("infdB").is_err());
+    }
+
+    #[test]
+    fn test_apply_volume_change_clamps() {
+        let config = VolumeConfig::default();
+
+        assert_eq!(
+            apply_volume_change(VolumeChange::Offset(0.3), 0.9, None, &config),
+            (1.0, None)
+        );
+        assert_eq!(
+            apply_volume_change(VolumeChange::Set(-2.0), 0.5, None, &config),
+            (0.0, None)
+        );
+
+        let config = VolumeConfig {
+            max: 1.5,
+            ..VolumeConfig::default()
+        };
+        assert_eq!(
+            apply_volume_change(VolumeChange::Set(1.2), 0.5, None, &config),
+            (1.2, None)
+        );
+    }
+
+    #[test]
+    fn test_apply_mute() {
+        let config = VolumeConfig::default();
+
+        assert_eq!(
+            apply_volume_change(VolumeChange::Mute, 0.7, None, &config),
+            (0.0, Some(0.7))
+        );
+        assert_eq!(
+            apply_volume_change(VolumeChange::Unmute, 0.0, Some(0.7), &config),
+            (0.7, None)
+        );
+        assert_eq!(
+            apply_volume_change(VolumeChange::ToggleMute, 0.0, Some(0.7), &config),
+            (0.7, None)
+        );
+        // unmuted by something else, so the saved volume is stale
+        assert_eq!(
+            apply_volume_change(VolumeChange::ToggleMute, 0.4, Some(0.7), &config),
+            (0.0, Some(0.4))
+        );
+    }
+
+    #[test]
+    fn test_muted_volumes_file() {
+        let volumes = HashMap::from([
+            (String::from("org.mpris.MediaPlayer2.mpv"), 0.5),
+            (String::from("org.mpris.MediaPlayer2.spotify"), 1.0),
+        ]);
+
+        let content = format_muted_volumes(&volumes);
+        assert_eq!(
+            content,
+            "org.mpris.MediaPlayer2.mpv 0.5\norg.mpris.MediaPlayer2.spotify 1\n"
+        );
+        assert_eq!(parse_muted_volumes(&content), volumes);
+    }
+}
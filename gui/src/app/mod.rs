@@ -7,6 +7,8 @@ use gtk::glib::{self, clone};
 use gtk::prelude::*;
 use gtk4 as gtk;
 
+use common::config::Config;
+use common::err::OrExit;
 use common::player::{PlayerAction, PlayerState};
 use std::process;
 use tokio::sync::mpsc as tokio_mpsc;
@@ -18,6 +20,7 @@ type MprisListener = dyn Fn(&PlayerState);
 
 pub struct App {
     gtk_app: gtk::Application,
+    pub config: Config,
     listeners: RefCell<Vec<Box<MprisListener /*---[*/>>>,
     most_recent_state: RefCell<Option<PlayerState>>,
     action_sender: RefCell<Option<mpsc::Sender<PlayerAction>>>,
@@ -27,10 +30,12 @@ pub struct App {
 impl App {
     pub fn new() -> Self {
         let gtk_app = gtk::Application::builder().application_id(APP_ID).build();
+        let config = common::config::load_config().or_exit("Failed to load config");
 
         App {
             most_recent_state: RefCell::new(None),
             gtk_app,
+            config,
             listeners: RefCell::new(Vec::new()),
             action_sender: RefCell::new(None),
         }
@@ -116,6 +121,7 @@ impl App {
         main_container.append(&ui::player_info::new(&self));
 
         window.set_child(Some(&main_container));
+        window.add_controller(ui::drop_target::new(self.clone()));
 
         window.present();
     }
@@ -124,6 +130,8 @@ impl App {
         let (player_tx, mut player_rx) = tokio_mpsc::channel(1);
         let (action_tx, action_rx) = mpsc::channel();
 
+        common::player::spawn_policies(&self.config).or_exit("Failed to start the policies");
+
         let player = common::player::get_preferred_player_or_first();
 
         match player {
@@ -2,13 +2,13 @@ use crate::app::WINDOW_WIDTH;
 
 use super::super::App;
 use anyhow::Result as AnyResult;
+use common::art::{download_art, get_cached_art_path, resolve_art_url, ArtSource};
 use glib::clone;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk4 as gtk;
 use image::io::Reader as ImageReader;
 use image::{DynamicImage, GenericImageView};
-use std::path::Path;
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::thread;
@@ -37,13 +37,12 @@ pub fn new(app: &App) -> gtk::Image {
             img.set_tooltip_text(state.metadata.album_name());
 
             if let Some(art_url) = state.metadata.art_url() {
-                if art_url.starts_with("file://") {
-                    let path = art_url.replace("file://", "");
-                    apply_art(img, Path::new(&path).to_path_buf(), css_provider.clone());
-                } else if art_url.starts_with("http://") || art_url.starts_with("https://") {
-                    handle_remote_art(img, art_url.to_string(), css_provider.clone());
-                } else {
-                    img.set_icon_name(None);
+                match resolve_art_url(art_url) {
+                    Some(ArtSource::Local(path)) => apply_art(img, path, css_provider.clone()),
+                    Some(ArtSource::Remote(url)) => {
+                        handle_remote_art(img, url, css_provider.clone())
+                    }
+                    None => img.set_icon_name(None),
                 }
             }
         }
@@ -92,10 +91,7 @@ fn apply_art(img: gtk::Image, path: PathBuf, css_provider: gtk::CssProvider) {
 }
 
 fn handle_remote_art(img: gtk::Image, art_url: String, css_provider: gtk::CssProvider) {
-    let escaped_url = urlencoding::encode(&art_url);
-    let path = Path::new("/tmp")
-        .join("rstroller")
-        .join(format!("{}", escaped_url));
+    let path = get_cached_art_path(&art_url);
 
     if path.exists() {
         apply_art(img, path, css_provider);
@@ -106,29 +102,16 @@ fn handle_remote_art(img: gtk::Image, art_url: String, css_provider: gtk::CssPro
 
     // that's not a "green thread", right? that's a messed up code. like the rest
     // of the code base, so its fine
-    thread::spawn(move || {
-        download_file(art_url, path, tx)
-            .map_err(|e| eprintln!("Failed to download album art: {}", e))
-            .unwrap();
+    thread::spawn(move || match download_art(&art_url, &path) {
+        Ok(()) => tx
+            .send(path)
+            .expect("Failed to send download completion signal"),
+        Err(e) => eprintln!("Failed to download album art: {}", e),
     });
 
     glib::spawn_future_local(async move {
-        let path = rx
-            .recv()
-            .expect("Failed to receive download completion signal");
-        apply_art(img, path, css_provider);
+        if let Ok(path) = rx.recv() {
+            apply_art(img, path, css_provider);
+        }
     });
 }
-
-fn download_file(url: String, dist_path: PathBuf, tx: mpsc::Sender<PathBuf>) -> AnyResult<()> {
-    dist_path.parent().map(std::fs::create_dir_all);
-
-    let url = reqwest::Url::parse(&url)?;
-    let mut resp = reqwest::blocking::get(url)?;
-    let mut file = std::fs::File::create(&dist_path)?;
-    std::io::copy(&mut resp, &mut file)?;
-
-    tx.send(dist_path)
-        .expect("Failed to send download completion signal");
-    Ok(())
-}
@@ -0,0 +1,41 @@
+use super::super::App;
+use common::player::PlayerAction;
+use glib::clone;
+use gtk::prelude::*;
+use gtk::{gdk, glib};
+use gtk4 as gtk;
+use std::rc::Rc;
+
+// the first dropped file, or the first line of the dropped text, which can be
+// a URL or a path
+fn dropped_uri(value: &glib::Value) -> Option<String> {
+    if let Ok(files) = value.get::<gdk::FileList>() {
+        return files.files().first().map(|file| file.uri().to_string());
+    }
+
+    let text = value.get::<String>().ok()?;
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    common::player::to_uri(line).ok()
+}
+
+/// Open the files and URLs dropped on the window in the player
+pub fn new(app: Rc<App>) -> gtk::DropTarget {
+    let drop_target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+    drop_target.set_types(&[gdk::FileList::static_type(), glib::Type::STRING]);
+
+    drop_target.connect_drop(clone!(
+        #[weak]
+        app,
+        #[upgrade_or]
+        false,
+        move |_, value, _, _| match dropped_uri(value) {
+            Some(uri) => {
+                app.send_action(PlayerAction::OpenUri(uri));
+                true
+            }
+            None => false,
+        }
+    ));
+
+    drop_target
+}
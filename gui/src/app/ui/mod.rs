@@ -3,3 +3,4 @@ pub mod player_progress;
 pub mod player_controller;
 pub mod album_art;
 pub mod player_info;
+pub mod drop_target;
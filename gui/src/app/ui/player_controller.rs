@@ -90,9 +90,18 @@ pub fn new(app: Rc<App>) -> gtk::Box {
 }
 
 fn create_volume_popover(app: Rc<App>, btn: &gtk::MenuButton) -> gtk::Popover {
+    // the same limits as the volume command
+    let limits = &app.config.volume;
     let scale = gtk::Scale::builder()
         .orientation(gtk::Orientation::Vertical)
-        .adjustment(&gtk::Adjustment::new(0.0, 0.0, 1.0, 0.01, 0.0, 0.0))
+        .adjustment(&gtk::Adjustment::new(
+            limits.min,
+            limits.min,
+            limits.max,
+            limits.step,
+            0.0,
+            0.0,
+        ))
         .inverted(true)
         .build();
     let popover = gtk::Popover::builder().child(&scale).build();
@@ -112,7 +121,7 @@ fn create_volume_popover(app: Rc<App>, btn: &gtk::MenuButton) -> gtk::Popover {
         app,
         move |scale| {
             let volume = scale.value();
-            let volume = (volume * 100.0).round() / 100.0;
+            let volume = app.config.volume.clamp((volume * 100.0).round() / 100.0);
 
             app.send_action(PlayerAction::Volume(volume));
         }